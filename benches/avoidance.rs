@@ -0,0 +1,37 @@
+use bevy::math::Vec2;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use drug_test::{separation_velocities, DEFAULT_SEPARATION_RADIUS, DEFAULT_SEPARATION_STRENGTH};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// The same settings the game uses, so the hash cells and neighbour counts are the real ones
+const RADIUS: f32 = DEFAULT_SEPARATION_RADIUS;
+const STRENGTH: f32 = DEFAULT_SEPARATION_STRENGTH;
+
+/// Agents scattered randomly over a square, always about as crowded no matter how many there are
+fn crowd(count: usize) -> Vec<Vec2> {
+    let mut rng = StdRng::seed_from_u64(3);
+    let side = (count as f32).sqrt() * RADIUS * 1.5;
+
+    (0..count)
+        .map(|_| Vec2::new(rng.gen_range(0.0..side), rng.gen_range(0.0..side)))
+        .collect()
+}
+
+fn separation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("separation");
+
+    for count in [100, 500, 2000] {
+        let positions = crowd(count);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &positions,
+            |b, positions| b.iter(|| separation_velocities(positions, RADIUS, STRENGTH)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, separation);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use bevy::{math::Vec2, utils::HashSet};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use drug_test::{bake_grid_nav_mesh, NavGrid};
+use navmesh::{NavPathMode, NavQuery, NavVec3};
+
+const AGENT_SIZE: Vec2 = Vec2::new(32., 120.);
+
+/// A grid of square rooms connected by doorways in the middle of every wall
+fn synthetic_grid(size: u32) -> NavGrid {
+    let room_size = 16;
+    let mut cells = HashSet::new();
+
+    for x in 1..size - 1 {
+        for y in 1..size - 1 {
+            let wall_x = x % room_size == 0;
+            let wall_y = y % room_size == 0;
+            let doorway_x = x % room_size == room_size / 2;
+            let doorway_y = y % room_size == room_size / 2;
+
+            if (!wall_x && !wall_y) || (wall_x && doorway_y) || (wall_y && doorway_x) {
+                cells.insert((x, y));
+            }
+        }
+    }
+
+    NavGrid {
+        cells,
+        width: size,
+        height: size,
+        grid_size: Vec2::splat(64.),
+        origin: Vec2::splat(32.),
+        ..Default::default()
+    }
+}
+
+fn bake(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bake");
+
+    for size in [64, 128, 256] {
+        let grid = synthetic_grid(size);
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), &grid, |b, grid| {
+            b.iter(|| bake_grid_nav_mesh(grid, AGENT_SIZE))
+        });
+    }
+
+    group.finish();
+}
+
+fn find_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_path");
+
+    for size in [64, 128, 256] {
+        let grid = synthetic_grid(size);
+        let nav_mesh = bake_grid_nav_mesh(&grid, AGENT_SIZE);
+
+        let from = grid.tile_center(2, 2);
+        let to = grid.tile_center(size - 3, size - 3);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &nav_mesh,
+            |b, nav_mesh| {
+                b.iter(|| {
+                    nav_mesh.find_path(
+                        NavVec3::new(from.x, from.y, 0.),
+                        NavVec3::new(to.x, to.y, 0.),
+                        NavQuery::Accuracy,
+                        NavPathMode::Accuracy,
+                    )
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bake, find_path);
+criterion_main!(benches);
@@ -12,9 +12,21 @@ fn impl_temporary(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
     let gen = quote! {
         impl Temporary for #name {
+            fn timer(&self) -> &Timer {
+                &self.timer
+            }
+
             fn get_timer(&mut self) -> &mut Timer {
                 &mut self.timer
             }
+
+            fn stacks(&self) -> u32 {
+                self.stacks
+            }
+
+            fn get_stacks(&mut self) -> &mut u32 {
+                &mut self.stacks
+            }
         }
     };
 
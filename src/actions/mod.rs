@@ -27,6 +27,7 @@ pub struct Actions {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BurstActions {
     Punch,
+    Interact,
     ConsumePill { index: usize },
 }
 
@@ -50,6 +51,10 @@ pub fn register_burst_actions(
         burst_actions.send(BurstActions::Punch);
     }
 
+    if keyboard_input.just_pressed(KeyCode::E) {
+        burst_actions.send(BurstActions::Interact);
+    }
+
     if keyboard_input.just_pressed(KeyCode::Key1) {
         burst_actions.send(BurstActions::ConsumePill { index: 0 });
     }
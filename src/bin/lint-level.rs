@@ -0,0 +1,70 @@
+//! Checks LDtk projects for problems without starting the game.
+//!
+//! Usage: `cargo run --bin lint-level assets/ldtk/level1.ldtk`
+
+use std::{env, fs, process::ExitCode};
+
+use bevy_ecs_ldtk::ldtk::LdtkJson;
+use drug_test::{lint_project, EnemyArchetypes};
+
+/// Where the enemy archetypes the levels refer to are, relative to the crate root
+const ARCHETYPES_PATH: &str = "assets/enemies/enemies.archetypes.ron";
+
+fn main() -> ExitCode {
+    let paths = env::args().skip(1).collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        eprintln!("Usage: lint-level <level.ldtk>...");
+        return ExitCode::from(2);
+    }
+
+    let archetypes = match fs::read_to_string(ARCHETYPES_PATH)
+        .map_err(|error| error.to_string())
+        .and_then(|ron| ron::from_str::<EnemyArchetypes>(&ron).map_err(|error| error.to_string()))
+    {
+        Ok(archetypes) => Some(archetypes),
+        Err(error) => {
+            eprintln!(
+                "{}: {}, enemy archetypes won't be checked",
+                ARCHETYPES_PATH, error
+            );
+            None
+        }
+    };
+
+    let mut failed = false;
+
+    for path in paths {
+        let project = match fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<LdtkJson>(&json).map_err(|error| error.to_string())
+            }) {
+            Ok(project) => project,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                failed = true;
+                continue;
+            }
+        };
+
+        let problems = lint_project(&project, archetypes.as_ref());
+
+        for problem in &problems {
+            println!("{}: {}", path, problem);
+        }
+
+        if problems.is_empty() {
+            println!("{}: ok", path);
+        } else {
+            println!("{}: {} problem(s) found", path, problems.len());
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
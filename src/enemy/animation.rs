@@ -2,6 +2,12 @@ use std::time::Duration;
 
 use bevy_spritesheet_animation::{animation_manager::AnimationManager, animation_graph::{AnimationTransitionCondition, AnimationTransitionMode}, animation::{Animation, AnimationBounds}};
 
+/// First and last frame of the stun animation
+const STUN_FRAMES: (usize, usize) = (20, 21);
+
+/// Frame the enemy is drawn with once it's knocked out, lying on the floor at the end of the stun
+pub const KNOCKED_OUT_FRAME: usize = STUN_FRAMES.1;
+
 pub fn enemy_animation_manager() -> AnimationManager {
 	let mut animation_manager = AnimationManager::new(
 		vec![
@@ -10,7 +16,7 @@ pub fn enemy_animation_manager() -> AnimationManager {
 			// Walking
 			Animation::new(AnimationBounds::new(0, 19), Duration::from_millis(80)),
 			// Stun
-			Animation::new(AnimationBounds::new(20, 21), Duration::from_millis(350)),
+			Animation::new(AnimationBounds::new(STUN_FRAMES.0, STUN_FRAMES.1), Duration::from_millis(350)),
 			// Shooting
 			Animation::new(AnimationBounds::new(22, 41), Duration::from_millis(20)),
 		],
@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use serde::Deserialize;
+
+use crate::{
+    loading::{EnemyAssets, TextureAssets},
+    unit::{Direction, Health, Movement},
+};
+
+use super::{
+    attack::EnemyAttackTimer,
+    sight::VisionCone,
+    weapon::{Weapon, WeaponStats},
+};
+
+/// The archetype enemies get when LDtk doesn't say otherwise
+pub const DEFAULT_ENEMY_ARCHETYPE: &str = "Guard";
+
+/// Highest stun resistance that counts, anything above it shakes stuns off just as fast
+pub const MAX_STUN_RESISTANCE: f32 = 0.95;
+
+/// Every enemy archetype from `assets/enemies/enemies.archetypes.ron`, by name
+#[derive(Deserialize, TypeUuid, Clone, Debug, Default, Deref)]
+#[uuid = "4f3a1c2e-8d6b-4a5f-9e7c-2b1d0a9f8e63"]
+#[serde(transparent)]
+pub struct EnemyArchetypes(pub HashMap<String, EnemyArchetype>);
+
+/// Balancing values shared by all the enemies of one kind
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EnemyArchetype {
+    pub speed: f32,
+    pub running_speed: f32,
+    pub health: f32,
+    /// How far the enemy can see, the vision cone fields in LDtk can still change it
+    pub sight_range: f32,
+    pub weapon: Weapon,
+    /// Replaces the damage of the weapon
+    pub attack_damage: Option<f32>,
+    /// Replaces the time between two attacks of the weapon, in seconds
+    pub attack_cooldown: Option<f32>,
+    /// Part of every stun the enemy shakes off, from 0 up to [`MAX_STUN_RESISTANCE`].
+    /// Values outside of that are clamped, so even 1 still leaves the enemy stunned for a bit.
+    pub stun_resistance: f32,
+    pub sprite_set: EnemySpriteSet,
+}
+
+impl Default for EnemyArchetype {
+    fn default() -> Self {
+        Self {
+            speed: 100.,
+            running_speed: 215.,
+            health: 75.,
+            sight_range: VisionCone::default().far_range,
+            weapon: Weapon::default(),
+            attack_damage: None,
+            attack_cooldown: None,
+            stun_resistance: 0.,
+            sprite_set: EnemySpriteSet::default(),
+        }
+    }
+}
+
+impl EnemyArchetype {
+    /// What the enemy's weapon ends up doing with the archetype's tweaks applied.
+    pub fn weapon_stats(&self, weapon: Weapon) -> WeaponStats {
+        let mut stats = weapon.stats();
+
+        if let Some(damage) = self.attack_damage {
+            stats.damage = damage;
+        }
+
+        if let Some(cooldown) = self.attack_cooldown {
+            stats.fire_interval = cooldown;
+        }
+
+        stats
+    }
+}
+
+/// Which textures the enemy is drawn with
+#[derive(Component, Reflect, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum EnemySpriteSet {
+    #[default]
+    Guard,
+}
+
+impl EnemySpriteSet {
+    pub fn texture_atlas(
+        &self,
+        textures: &TextureAssets,
+        direction: Direction,
+    ) -> Handle<TextureAtlas> {
+        match self {
+            EnemySpriteSet::Guard => match direction {
+                Direction::Up => textures.enemy_up.clone(),
+                Direction::Down => textures.enemy_down.clone(),
+                Direction::Left => textures.enemy_left.clone(),
+                Direction::Right => textures.enemy_right.clone(),
+            },
+        }
+    }
+}
+
+/// Name of the archetype the enemy is built from, set by the `Archetype` field in LDtk
+#[derive(Component, Reflect, Clone, Debug, Deref, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct EnemyArchetypeName(pub String);
+
+impl Default for EnemyArchetypeName {
+    fn default() -> Self {
+        Self(DEFAULT_ENEMY_ARCHETYPE.to_string())
+    }
+}
+
+/// Tweaks the level designer made to a single enemy in LDtk, they win over the archetype
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct ArchetypeOverrides {
+    pub weapon: Option<Weapon>,
+    pub vision_half_angle: Option<f32>,
+    pub vision_near_range: Option<f32>,
+    pub vision_far_range: Option<f32>,
+}
+
+impl ArchetypeOverrides {
+    pub fn vision_cone(&self, base: VisionCone) -> VisionCone {
+        VisionCone {
+            half_angle: self.vision_half_angle.unwrap_or(base.half_angle),
+            near_range: self.vision_near_range.unwrap_or(base.near_range),
+            far_range: self.vision_far_range.unwrap_or(base.far_range),
+        }
+    }
+}
+
+/// Part of every stun the enemy shakes off, capped at [`MAX_STUN_RESISTANCE`]
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Deref)]
+#[reflect(Component)]
+pub struct StunResistance(pub f32);
+
+impl StunResistance {
+    /// How much faster than normal the enemy's stuns run out
+    pub fn recovery_speed(&self) -> f32 {
+        1. / (1. - self.0.clamp(0., MAX_STUN_RESISTANCE))
+    }
+}
+
+/// Sets up freshly spawned enemies from their archetypes, and every enemy again
+/// when the archetype file changes.
+pub fn apply_enemy_archetypes(
+    mut enemy_query: Query<(
+        Ref<EnemyArchetypeName>,
+        &ArchetypeOverrides,
+        &mut Movement,
+        &mut Health,
+        &mut VisionCone,
+        &mut Weapon,
+        &mut WeaponStats,
+        &mut EnemyAttackTimer,
+        &mut StunResistance,
+        &mut EnemySpriteSet,
+    )>,
+    mut archetype_events: EventReader<AssetEvent<EnemyArchetypes>>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    enemy_assets: Res<EnemyAssets>,
+) {
+    let reloaded = archetype_events
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }));
+
+    let archetypes = if let Some(archetypes) = archetypes.get(&enemy_assets.archetypes) {
+        archetypes
+    } else {
+        return;
+    };
+
+    for (
+        archetype_name,
+        overrides,
+        mut movement,
+        mut health,
+        mut vision_cone,
+        mut weapon,
+        mut weapon_stats,
+        mut attack_timer,
+        mut stun_resistance,
+        mut sprite_set,
+    ) in enemy_query.iter_mut()
+    {
+        let spawned = archetype_name.is_added();
+
+        if !spawned && !reloaded {
+            continue;
+        }
+
+        let archetype = if let Some(archetype) = archetypes.get(&archetype_name.0) {
+            archetype.clone()
+        } else {
+            warn!(
+                "Unknown enemy archetype {}, using the default one",
+                archetype_name.0
+            );
+            EnemyArchetype::default()
+        };
+
+        movement.speed = archetype.speed;
+        movement.running_speed = archetype.running_speed;
+
+        // Reloading the file doesn't heal the enemies already in the level
+        if spawned {
+            *health = Health::new(archetype.health);
+        }
+
+        let mut new_vision_cone = overrides.vision_cone(VisionCone {
+            far_range: archetype.sight_range,
+            ..default()
+        });
+
+        if new_vision_cone.near_range > new_vision_cone.far_range {
+            warn!(
+                "Enemy archetype {} sees only {} far, less than its near range",
+                archetype_name.0, new_vision_cone.far_range
+            );
+            new_vision_cone.near_range = new_vision_cone.far_range;
+        }
+
+        *vision_cone = new_vision_cone;
+
+        *weapon = overrides.weapon.unwrap_or(archetype.weapon);
+        *weapon_stats = archetype.weapon_stats(*weapon);
+
+        attack_timer.set_duration(Duration::from_secs_f32(weapon_stats.fire_interval));
+
+        *stun_resistance = StunResistance(archetype.stun_resistance);
+        sprite_set.set_if_neq(archetype.sprite_set);
+    }
+}
@@ -1,17 +1,24 @@
 use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioControl};
 use bevy_spritesheet_animation::animation_manager::{transition_animations, AnimationManager};
+use rand::Rng;
 
 use crate::{
     player::{
-        effect::{Invincibility, Vulnerability},
+        effect::{apply_player_damage, Invincibility, Vulnerability},
         Player,
     },
+    rng::GameRng,
     unit::Health,
     GameState, loading::AudioAssets,
 };
 
-use super::{sight::see_player, EnemyState};
+use super::{
+    hearing::NoiseEvent,
+    sight::see_player,
+    weapon::{scatter_shot, ProjectileBundle, WeaponStats},
+    EnemyState,
+};
 
 pub struct EnemyAttackPlugin;
 
@@ -37,11 +44,13 @@ impl Default for EnemyAttackTimer {
 }
 
 fn attack_player(
+    mut commands: Commands,
     mut enemy_query: Query<(
         &mut EnemyState,
         &mut EnemyAttackTimer,
         &mut AnimationManager,
         &Transform,
+        &WeaponStats,
     )>,
     mut player_query: Query<
         (
@@ -56,11 +65,15 @@ fn attack_player(
     audio: Res<Audio>,
     audio_assets: Res<AudioAssets>,
     mut state: ResMut<NextState<GameState>>,
+    mut noise_events: EventWriter<NoiseEvent>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     let (player_transform, mut player_health, player_invincibility, player_vulnerability) =
         player_query.single_mut();
 
-    for (mut enemy_state, mut enemy_timer, mut animation_manager, enemy_transform) in
+    let player_position = player_transform.translation.truncate();
+
+    for (mut enemy_state, mut enemy_timer, mut animation_manager, enemy_transform, stats) in
         enemy_query.iter_mut()
     {
         animation_manager
@@ -71,41 +84,48 @@ fn attack_player(
             continue;
         }
 
-        if matches!(*enemy_state, EnemyState::Alert { .. })
-            && player_transform
-                .translation
-                .truncate()
-                .distance(enemy_transform.translation.truncate())
-                < 25.
+        let enemy_position = enemy_transform.translation.truncate();
+
+        if !matches!(*enemy_state, EnemyState::Alert { .. })
+            || player_position.distance(enemy_position) > stats.range
         {
-            enemy_timer.tick(time.delta());
-
-            if enemy_timer.just_finished() {
-                if *player_health.take_damage(
-                    (rand::random::<f32>() * 5.0
-                        + 20.0
-                            * if let Some(vulnerability) = player_vulnerability {
-                                vulnerability.amount
-                            } else {
-                                1.0
-                            })
-                        * if player_invincibility.is_some() {
-                            0.0
-                        } else {
-                            1.0
-                        },
-                ) {
-                    state.set(GameState::GameOver);
-
-                    *enemy_state = EnemyState::Idle;
-                }
-
-                animation_manager
-                    .set_state("shoot".to_string(), true)
-                    .unwrap();
-
-                audio.play(audio_assets.shot.clone());
+            continue;
+        }
+
+        enemy_timer.tick(time.delta());
+
+        if !enemy_timer.just_finished() {
+            continue;
+        }
+
+        if stats.is_ranged() {
+            for direction in scatter_shot(
+                player_position - enemy_position,
+                stats,
+                &mut game_rng.combat,
+            ) {
+                commands.spawn(ProjectileBundle::new(enemy_position, direction, stats));
             }
+        } else if *apply_player_damage(
+            &mut player_health,
+            stats.damage + game_rng.combat.gen::<f32>() * 5.0,
+            player_invincibility,
+            player_vulnerability,
+        ) {
+            state.set(GameState::GameOver);
+
+            *enemy_state = EnemyState::Idle;
         }
+
+        animation_manager
+            .set_state("shoot".to_string(), true)
+            .unwrap();
+
+        noise_events.send(NoiseEvent {
+            position: enemy_position,
+            loudness: 600.,
+        });
+
+        audio.play(audio_assets.shot.clone());
     }
 }
@@ -0,0 +1,308 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::GameState;
+
+use super::{movement::enemy_movement, EnemyState, ENEMY_COLLIDER_WIDTH};
+
+pub struct EnemyAvoidancePlugin;
+
+impl Plugin for EnemyAvoidancePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Separation>()
+            .init_resource::<AvoidanceSettings>()
+            .add_system(
+                steer_apart
+                    .before(enemy_movement)
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+/// A bit more than an enemy is wide, so they start making room before they touch
+pub const DEFAULT_SEPARATION_RADIUS: f32 = ENEMY_COLLIDER_WIDTH * 1.25;
+pub const DEFAULT_SEPARATION_STRENGTH: f32 = 120.;
+
+/// How enemies keep out of each other's way
+#[derive(Resource, Clone, Debug)]
+pub struct AvoidanceSettings {
+    /// Enemies closer than this push each other away
+    pub radius: f32,
+    /// How fast two enemies standing on top of each other get pushed apart
+    pub strength: f32,
+}
+
+impl Default for AvoidanceSettings {
+    fn default() -> Self {
+        Self {
+            radius: DEFAULT_SEPARATION_RADIUS,
+            strength: DEFAULT_SEPARATION_STRENGTH,
+        }
+    }
+}
+
+/// Velocity pushing the enemy away from the ones around it, blended into its path following
+#[derive(Reflect, Component, Clone, Copy, Default, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct Separation(pub Vec2);
+
+/// Buckets points into square cells, so finding the ones near a point
+/// only has to look at a few cells instead of every point.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32, points: &[Vec2]) -> Self {
+        let mut cells = HashMap::<(i32, i32), Vec<usize>>::default();
+
+        for (index, point) in points.iter().enumerate() {
+            cells
+                .entry(Self::cell(cell_size, *point))
+                .or_default()
+                .push(index);
+        }
+
+        Self { cell_size, cells }
+    }
+
+    fn cell(cell_size: f32, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / cell_size).floor() as i32,
+            (point.y / cell_size).floor() as i32,
+        )
+    }
+
+    /// Indices of the points that might be within `radius` of the point.
+    /// Some of them can be further away, but none of the closer ones are left out.
+    pub fn candidates(&self, point: Vec2, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let (min_x, min_y) = Self::cell(self.cell_size, point - Vec2::splat(radius));
+        let (max_x, max_y) = Self::cell(self.cell_size, point + Vec2::splat(radius));
+
+        (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Boids-style separation: every agent gets pushed away from the ones within `radius`,
+/// the harder the closer they are.
+///
+/// # Returns
+/// The separation velocity of every agent, in the order of `positions`.
+pub fn separation_velocities(positions: &[Vec2], radius: f32, strength: f32) -> Vec<Vec2> {
+    let spatial_hash = SpatialHash::new(radius, positions);
+
+    positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| {
+            let push = spatial_hash
+                .candidates(*position, radius)
+                .filter(|other| *other != index)
+                .map(|other| {
+                    let away = *position - positions[other];
+                    let distance = away.length();
+
+                    if distance >= radius {
+                        Vec2::ZERO
+                    } else if distance == 0. {
+                        // Right on top of each other, the lower index goes one way and the higher the other
+                        if index < other {
+                            Vec2::X
+                        } else {
+                            Vec2::NEG_X
+                        }
+                    } else {
+                        away / distance * (1. - distance / radius)
+                    }
+                })
+                .sum::<Vec2>();
+
+            push.clamp_length_max(1.) * strength
+        })
+        .collect()
+}
+
+fn steer_apart(
+    mut enemy_query: Query<(&Transform, &mut Separation), With<EnemyState>>,
+    settings: Res<AvoidanceSettings>,
+) {
+    let positions = enemy_query
+        .iter()
+        .map(|(transform, _)| transform.translation.truncate())
+        .collect::<Vec<_>>();
+
+    let velocities = separation_velocities(&positions, settings.radius, settings.strength);
+
+    for ((_, mut separation), velocity) in enemy_query.iter_mut().zip(velocities) {
+        separation.set_if_neq(Separation(velocity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    fn random_points(count: usize, extent: f32, seed: u64) -> Vec<Vec2> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        (0..count)
+            .map(|_| {
+                Vec2::new(
+                    rng.gen_range(-extent..extent),
+                    rng.gen_range(-extent..extent),
+                )
+            })
+            .collect()
+    }
+
+    /// The same push as `separation_velocities`, checking every pair of agents.
+    fn brute_force_separation(positions: &[Vec2], radius: f32, strength: f32) -> Vec<Vec2> {
+        positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| {
+                let push = positions
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(other, other_position)| {
+                        let away = *position - *other_position;
+                        let distance = away.length();
+
+                        if distance >= radius {
+                            Vec2::ZERO
+                        } else if distance == 0. {
+                            if index < other {
+                                Vec2::X
+                            } else {
+                                Vec2::NEG_X
+                            }
+                        } else {
+                            away / distance * (1. - distance / radius)
+                        }
+                    })
+                    .sum::<Vec2>();
+
+                push.clamp_length_max(1.) * strength
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spatial_hash_finds_every_neighbour() {
+        let points = random_points(300, 200., 1);
+        let radius = 25.;
+        let spatial_hash = SpatialHash::new(radius, &points);
+
+        for point in &points {
+            let candidates = spatial_hash.candidates(*point, radius).collect::<Vec<_>>();
+
+            for (index, other) in points.iter().enumerate() {
+                if point.distance(*other) <= radius {
+                    assert!(
+                        candidates.contains(&index),
+                        "{:?} is missing its neighbour {:?}",
+                        point,
+                        other
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spatial_hash_finds_neighbours_across_cell_borders() {
+        // Both sides of the origin, where the cell coordinates go negative
+        let points = [Vec2::new(-0.5, -0.5), Vec2::new(0.5, 0.5)];
+        let spatial_hash = SpatialHash::new(10., &points);
+
+        let candidates = spatial_hash.candidates(points[0], 2.).collect::<Vec<_>>();
+
+        assert!(candidates.contains(&0));
+        assert!(candidates.contains(&1));
+    }
+
+    #[test]
+    fn spatial_hash_candidates_are_not_repeated() {
+        let points = random_points(100, 50., 2);
+        let spatial_hash = SpatialHash::new(10., &points);
+
+        let mut candidates = spatial_hash.candidates(Vec2::ZERO, 30.).collect::<Vec<_>>();
+        let count = candidates.len();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        assert_eq!(candidates.len(), count);
+    }
+
+    #[test]
+    fn separation_matches_checking_every_pair() {
+        let positions = random_points(200, 150., 3);
+
+        let velocities = separation_velocities(&positions, 25., 120.);
+        let expected = brute_force_separation(&positions, 25., 120.);
+
+        for (velocity, expected) in velocities.iter().zip(&expected) {
+            assert!(
+                velocity.abs_diff_eq(*expected, 1e-3),
+                "{:?} != {:?}",
+                velocity,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn separation_pushes_close_agents_apart() {
+        let positions = [Vec2::ZERO, Vec2::new(10., 0.)];
+
+        let velocities = separation_velocities(&positions, 25., 120.);
+
+        assert!(velocities[0].x < 0.);
+        assert!(velocities[1].x > 0.);
+        assert!(velocities[0].abs_diff_eq(-velocities[1], 1e-4));
+    }
+
+    #[test]
+    fn separation_ignores_agents_out_of_range() {
+        let positions = [Vec2::ZERO, Vec2::new(30., 0.)];
+
+        let velocities = separation_velocities(&positions, 25., 120.);
+
+        assert_eq!(velocities, vec![Vec2::ZERO, Vec2::ZERO]);
+    }
+
+    #[test]
+    fn separation_splits_coincident_agents() {
+        let positions = [Vec2::new(5., 5.), Vec2::new(5., 5.)];
+
+        let velocities = separation_velocities(&positions, 25., 120.);
+
+        assert_eq!(velocities[0], Vec2::X * 120.);
+        assert_eq!(velocities[1], Vec2::NEG_X * 120.);
+    }
+
+    #[test]
+    fn separation_is_capped_at_strength() {
+        // Surrounded on every side but one, all the pushes add up in the same direction
+        let positions = [
+            Vec2::ZERO,
+            Vec2::new(-5., 0.),
+            Vec2::new(-4., 3.),
+            Vec2::new(-4., -3.),
+            Vec2::new(0., 5.),
+            Vec2::new(0., -5.),
+        ];
+
+        let velocities = separation_velocities(&positions, 25., 120.);
+
+        assert!(velocities[0].length() <= 120. + 1e-3);
+        assert!(velocities[0].x > 0.);
+    }
+}
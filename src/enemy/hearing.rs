@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use navmesh::{NavPathMode, NavQuery, NavVec3};
+
+use crate::{
+    level::{
+        nav_agent::{nav_mesh_for_agent, AgentClass, AgentLevel},
+        WorldNavMesh,
+    },
+    GameState,
+};
+
+use super::{
+    movement::{EnemyMovementTarget, EnemySearchSettings},
+    EnemyState,
+};
+
+pub struct EnemyHearingPlugin;
+
+impl Plugin for EnemyHearingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NoiseEvent>()
+            .add_system(hear_noises.in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+/// Something enemies can hear, like a sneeze or a gunshot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoiseEvent {
+    pub position: Vec2,
+    /// How far the noise carries, measured along the way around the walls
+    pub loudness: f32,
+}
+
+/// Length of the shortest walkable way between the two points on the nav mesh.
+fn path_distance(nav_mesh: &WorldNavMesh, from: Vec2, to: Vec2) -> Option<f32> {
+    let to = nav_mesh.closest_point(NavVec3::new(to.x, to.y, 0.), NavQuery::Closest)?;
+
+    let path = nav_mesh.find_path(
+        NavVec3::new(from.x, from.y, 0.),
+        to,
+        NavQuery::Accuracy,
+        NavPathMode::Accuracy,
+    )?;
+
+    Some(
+        path.windows(2)
+            .map(|segment| (segment[1] - segment[0]).magnitude())
+            .sum(),
+    )
+}
+
+/// Sends the enemies that hear a noise to look around where it came from.
+fn hear_noises(
+    mut enemy_query: Query<(
+        &mut EnemyState,
+        &mut EnemyMovementTarget,
+        &Transform,
+        &AgentClass,
+        &AgentLevel,
+    )>,
+    nav_mesh_query: Query<&WorldNavMesh>,
+    mut noise_events: EventReader<NoiseEvent>,
+    search_settings: Res<EnemySearchSettings>,
+) {
+    for noise in noise_events.iter() {
+        for (
+            mut enemy_state,
+            mut enemy_movement_target,
+            enemy_transform,
+            agent_class,
+            agent_level,
+        ) in enemy_query.iter_mut()
+        {
+            // Stunned enemies don't hear anything and alerted ones are busy chasing the player
+            if matches!(
+                *enemy_state,
+                EnemyState::Stun { .. } | EnemyState::Alert { .. }
+            ) {
+                continue;
+            }
+
+            let enemy_position = enemy_transform.translation.truncate();
+
+            // The way around the walls is never shorter than the straight line
+            if enemy_position.distance(noise.position) > noise.loudness {
+                continue;
+            }
+
+            let nav_mesh = if let Some(nav_mesh) =
+                nav_mesh_for_agent(&nav_mesh_query, agent_level, *agent_class)
+            {
+                nav_mesh
+            } else {
+                continue;
+            };
+
+            let heard = path_distance(nav_mesh, enemy_position, noise.position)
+                .map(|distance| distance <= noise.loudness)
+                .unwrap_or(false);
+
+            if heard {
+                *enemy_state = EnemyState::Search {
+                    last_known: noise.position,
+                    timer: Timer::from_seconds(search_settings.duration, TimerMode::Once),
+                };
+
+                // Dropping whatever it was doing to go and check it out
+                enemy_movement_target.path.clear();
+            }
+        }
+    }
+}
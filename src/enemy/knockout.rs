@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{QueryFilter, RapierContext};
+
+use crate::{
+    unit::{Direction, Health},
+    GameState, WorldState,
+};
+
+use super::{
+    animation::KNOCKED_OUT_FRAME,
+    movement::{EnemyMovementTarget, EnemySearchSettings},
+    sight::{see_player, Suspicion, VisionCone},
+    EnemyState,
+};
+
+pub struct EnemyKnockoutPlugin;
+
+impl Plugin for EnemyKnockoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<EnemyBody>()
+            .add_system(knock_out_enemies.in_set(OnUpdate(WorldState::Yes)))
+            .add_system(
+                notice_bodies
+                    .after(see_player)
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+/// What's left of an enemy after the player knocks it out
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component)]
+pub struct EnemyBody {
+    /// Enemies that already found this body, so they don't keep searching around it forever
+    pub noticed_by: Vec<Entity>,
+}
+
+fn knock_out_enemies(
+    mut commands: Commands,
+    enemy_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Health,
+            &Handle<TextureAtlas>,
+            Option<&Parent>,
+        ),
+        With<EnemyState>,
+    >,
+) {
+    for (enemy_entity, enemy_transform, enemy_health, texture_atlas, parent) in enemy_query.iter() {
+        if enemy_health.get_health() > 0. {
+            continue;
+        }
+
+        commands.entity(enemy_entity).despawn_recursive();
+
+        let body_entity = commands
+            .spawn((
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: KNOCKED_OUT_FRAME,
+                        color: Color::rgb(0.7, 0.7, 0.7),
+                        ..default()
+                    },
+                    texture_atlas: texture_atlas.clone(),
+                    // Lying on the floor, below everyone who's still standing
+                    transform: Transform {
+                        translation: enemy_transform.translation.truncate().extend(3.),
+                        ..*enemy_transform
+                    },
+                    ..default()
+                },
+                EnemyBody::default(),
+                Name::new("Enemy Body"),
+            ))
+            .id();
+
+        // The body takes the enemy's place in the level, so it goes away together with the level
+        // and its transform stays relative to the same parent
+        if let Some(parent) = parent {
+            commands.entity(parent.get()).add_child(body_entity);
+        }
+    }
+}
+
+/// Enemies that see a knocked out colleague go looking for whoever did it.
+fn notice_bodies(
+    mut enemy_query: Query<(
+        Entity,
+        &mut EnemyState,
+        &mut EnemyMovementTarget,
+        &mut Suspicion,
+        &VisionCone,
+        &Transform,
+        &Direction,
+    )>,
+    mut body_query: Query<(&mut EnemyBody, &Transform)>,
+    rapier_context: Res<RapierContext>,
+    search_settings: Res<EnemySearchSettings>,
+) {
+    for (mut body, body_transform) in body_query.iter_mut() {
+        let body_position = body_transform.translation.truncate();
+
+        for (
+            enemy_entity,
+            mut enemy_state,
+            mut enemy_movement_target,
+            mut suspicion,
+            vision_cone,
+            enemy_transform,
+            enemy_direction,
+        ) in enemy_query.iter_mut()
+        {
+            if body.noticed_by.contains(&enemy_entity)
+                || matches!(
+                    *enemy_state,
+                    EnemyState::Alert { .. } | EnemyState::Stun { .. }
+                )
+            {
+                continue;
+            }
+
+            let enemy_position = enemy_transform.translation.truncate();
+            let to_body_vector = body_position - enemy_position;
+            let distance = to_body_vector.length();
+
+            let angle = if distance == 0. {
+                0.
+            } else {
+                enemy_direction
+                    .as_vec2()
+                    .angle_between(to_body_vector)
+                    .abs()
+            };
+
+            if vision_cone.visibility(distance, angle) <= 0. {
+                continue;
+            }
+
+            // The body doesn't have a collider, so anything hit on the way is in front of it
+            let blocked = rapier_context
+                .cast_ray(
+                    enemy_position,
+                    to_body_vector.normalize_or_zero(),
+                    distance,
+                    true,
+                    QueryFilter::new().exclude_sensors(),
+                )
+                .is_some();
+
+            if blocked {
+                continue;
+            }
+
+            body.noticed_by.push(enemy_entity);
+
+            *enemy_state = EnemyState::Search {
+                last_known: body_position,
+                timer: Timer::from_seconds(search_settings.duration, TimerMode::Once),
+            };
+
+            // Anything that moves from now on is going to look a lot more suspicious
+            **suspicion = suspicion.max(0.5);
+
+            enemy_movement_target.path.clear();
+        }
+    }
+}
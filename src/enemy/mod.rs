@@ -9,36 +9,69 @@ use bevy_spritesheet_animation::animation_manager::AnimationManager;
 
 use crate::{
     cleanup::cleanup,
+    level::nav_agent::{assign_agent_class, assign_agent_level, AgentClass},
     loading::TextureAssets,
-    unit::{Direction, Movement},
+    unit::{Direction, Health, Movement},
     WorldState,
 };
 
 use self::{
     animation::enemy_animation_manager,
+    archetype::{
+        apply_enemy_archetypes, ArchetypeOverrides, EnemyArchetype, EnemyArchetypeName,
+        EnemySpriteSet, StunResistance, DEFAULT_ENEMY_ARCHETYPE,
+    },
     attack::{EnemyAttackPlugin, EnemyAttackTimer},
-    movement::{EnemyMovementPlugin, EnemyMovementTarget, EnemyMovementType},
-    sight::EnemySightPlugin,
+    avoidance::{EnemyAvoidancePlugin, Separation},
+    hearing::EnemyHearingPlugin,
+    knockout::EnemyKnockoutPlugin,
+    movement::{
+        ApproachOffset, EnemyMovementPlugin, EnemyMovementTarget, EnemyMovementType, PatrolMode,
+        Waypoint,
+    },
+    radio::EnemyRadioPlugin,
+    sight::{EnemySightPlugin, Suspicion, VisionCone},
+    sight_cone::SightConePlugin,
+    weapon::{EnemyWeaponPlugin, Weapon, WeaponStats},
 };
 
 mod animation;
+pub mod archetype;
 mod attack;
+pub mod avoidance;
+pub mod hearing;
+mod knockout;
 mod movement;
+mod radio;
 mod sight;
+mod sight_cone;
+mod weapon;
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<EnemyState>()
+            .register_type::<EnemyArchetypeName>()
+            .register_type::<EnemySpriteSet>()
+            .register_type::<StunResistance>()
             .add_plugin(EnemyMovementPlugin)
+            .add_plugin(EnemyAvoidancePlugin)
             .add_plugin(EnemySightPlugin)
             .add_plugin(EnemyAttackPlugin)
+            .add_plugin(EnemyHearingPlugin)
+            .add_plugin(EnemyRadioPlugin)
+            .add_plugin(SightConePlugin)
+            .add_plugin(EnemyWeaponPlugin)
+            .add_plugin(EnemyKnockoutPlugin)
             .add_systems(
                 (
                     update_sprites,
                     update_z_index,
                     adjust_enemy_scale,
+                    assign_agent_class.after(adjust_enemy_scale),
+                    assign_agent_level,
+                    apply_enemy_archetypes.before(update_sprites),
                     handle_stunned_enemies,
                 )
                     .in_set(OnUpdate(WorldState::Yes)),
@@ -55,6 +88,11 @@ pub enum EnemyState {
     Alert {
         target: Vec2,
     },
+    /// Looking around the place the player was last seen
+    Search {
+        last_known: Vec2,
+        timer: Timer,
+    },
     Stun {
         timer: Timer,
     },
@@ -66,6 +104,7 @@ pub struct EnemyBundle {
     sprite_sheet_bundle: SpriteSheetBundle,
     movement: Movement,
     direction: Direction,
+    health: Health,
     rigidbody: RigidBody,
     collider: Collider,
     sensor: Sensor,
@@ -74,6 +113,17 @@ pub struct EnemyBundle {
     movement_target: EnemyMovementTarget,
     animation_manager: AnimationManager,
     attack_timer: EnemyAttackTimer,
+    weapon: Weapon,
+    weapon_stats: WeaponStats,
+    archetype_name: EnemyArchetypeName,
+    archetype_overrides: ArchetypeOverrides,
+    stun_resistance: StunResistance,
+    sprite_set: EnemySpriteSet,
+    agent_class: AgentClass,
+    vision_cone: VisionCone,
+    suspicion: Suspicion,
+    approach_offset: ApproachOffset,
+    separation: Separation,
 }
 
 pub const ENEMY_COLLIDER_WIDTH: f32 = 32.;
@@ -82,14 +132,17 @@ pub const ENEMY_COLLIDER_HEIGHT: f32 = 120.;
 impl Default for EnemyBundle {
     fn default() -> Self {
         let animation_manager = enemy_animation_manager();
+        // The archetype replaces these once the enemy spawns
+        let archetype = EnemyArchetype::default();
 
         Self {
             sprite_sheet_bundle: SpriteSheetBundle::default(),
             movement: Movement {
-                speed: 100.,
-                running_speed: 215.,
+                speed: archetype.speed,
+                running_speed: archetype.running_speed,
             },
             direction: Direction::default(),
+            health: Health::new(archetype.health),
             rigidbody: RigidBody::KinematicPositionBased,
             collider: Collider::cuboid(ENEMY_COLLIDER_WIDTH, ENEMY_COLLIDER_HEIGHT),
             sensor: Sensor,
@@ -97,7 +150,21 @@ impl Default for EnemyBundle {
             movement_type: EnemyMovementType::Static { target: Vec2::ZERO },
             movement_target: EnemyMovementTarget::default(),
             animation_manager,
-            attack_timer: EnemyAttackTimer(Timer::from_seconds(1., TimerMode::Repeating)),
+            attack_timer: EnemyAttackTimer(Timer::from_seconds(
+                archetype.weapon_stats(archetype.weapon).fire_interval,
+                TimerMode::Repeating,
+            )),
+            weapon: archetype.weapon,
+            weapon_stats: archetype.weapon_stats(archetype.weapon),
+            archetype_name: EnemyArchetypeName::default(),
+            archetype_overrides: ArchetypeOverrides::default(),
+            stun_resistance: StunResistance(archetype.stun_resistance),
+            sprite_set: archetype.sprite_set,
+            agent_class: AgentClass::default(),
+            vision_cone: VisionCone::default(),
+            suspicion: Suspicion::default(),
+            approach_offset: ApproachOffset::default(),
+            separation: Separation::default(),
         }
     }
 }
@@ -111,102 +178,293 @@ impl LdtkEntity for EnemyBundle {
         _: &AssetServer,
         _: &mut Assets<TextureAtlas>,
     ) -> Self {
-        let mut movement_type_str = "".to_string();
-        let position = ldtk_pixel_coords_to_translation_pivoted(
-            entity_instance.px,
+        // Problems are reported by the level validation, here we just take the fallback
+        let (enemy_fields, _problems) = parse_enemy_fields(entity_instance, layer_instance);
+
+        Self {
+            movement_type: enemy_fields.movement_type,
+            vision_cone: enemy_fields.overrides.vision_cone(VisionCone::default()),
+            archetype_name: EnemyArchetypeName(enemy_fields.archetype),
+            archetype_overrides: enemy_fields.overrides,
+            ..default()
+        }
+    }
+}
+
+/// Enemy configuration set in LDtk
+#[derive(Clone, Debug)]
+pub struct EnemyFields {
+    pub movement_type: EnemyMovementType,
+    /// Name of the archetype from the archetype file
+    pub archetype: String,
+    pub overrides: ArchetypeOverrides,
+}
+
+/// Reads the enemy configuration from its LDtk fields.
+///
+/// Invalid fields don't prevent the enemy from spawning. The enemy falls back
+/// to standing still with just the archetype's vision and weapon and the problems are returned alongside it.
+pub fn parse_enemy_fields(
+    entity_instance: &EntityInstance,
+    layer_instance: &LayerInstance,
+) -> (EnemyFields, Vec<String>) {
+    let mut problems = Vec::new();
+
+    let point_to_translation = |point: IVec2| {
+        ldtk_pixel_coords_to_translation_pivoted(
+            point * layer_instance.grid_size,
             layer_instance.c_hei * layer_instance.grid_size,
             IVec2::new(entity_instance.width, entity_instance.height),
             entity_instance.pivot,
-        );
+        )
+    };
 
-        let mut path = vec![position];
-        let mut area_corner_1 = None;
-        let mut area_corner_2 = None;
+    let position = ldtk_pixel_coords_to_translation_pivoted(
+        entity_instance.px,
+        layer_instance.c_hei * layer_instance.grid_size,
+        IVec2::new(entity_instance.width, entity_instance.height),
+        entity_instance.pivot,
+    );
 
-        for field in &entity_instance.field_instances {
-            match field.identifier.as_str() {
-                "Enemy_Type" => match &field.value {
-                    FieldValue::Enum(value) => {
-                        movement_type_str = value.clone().expect("Movement type is null");
-                    }
-                    other => panic!("Unknown movement type: {:?}", other),
-                },
-                "Path" => match &field.value {
-                    FieldValue::Points(value) => {
-                        let path_points = value
-                            .into_iter()
-                            .map(|point| {
-                                let point = point.expect("Empty point in an enemy path!");
-
-                                ldtk_pixel_coords_to_translation_pivoted(
-                                    point * layer_instance.grid_size,
-                                    layer_instance.c_hei * layer_instance.grid_size,
-                                    IVec2::new(entity_instance.width, entity_instance.height),
-                                    entity_instance.pivot,
-                                )
-                            })
-                            .collect::<Vec<_>>();
-
-                        for point in path_points {
-                            path.push(point);
+    let mut movement_type_str = None;
+    let mut path = vec![position];
+    let mut patrol_mode = PatrolMode::default();
+    let mut wait_times = Vec::new();
+    let mut facings = Vec::new();
+    let mut area_corner_1 = None;
+    let mut area_corner_2 = None;
+    let mut archetype = DEFAULT_ENEMY_ARCHETYPE.to_string();
+    let mut overrides = ArchetypeOverrides::default();
+
+    // Empty number fields keep the default value
+    let number_field = |value: &FieldValue| match value {
+        FieldValue::Float(value) => Ok(*value),
+        FieldValue::Int(value) => Ok(value.map(|value| value as f32)),
+        other => Err(other.clone()),
+    };
+
+    for field in &entity_instance.field_instances {
+        match field.identifier.as_str() {
+            "Enemy_Type" => match &field.value {
+                FieldValue::Enum(value) => {
+                    movement_type_str = value.clone();
+                }
+                other => problems.push(format!("Enemy_Type is not an enum: {:?}", other)),
+            },
+            "Path" => match &field.value {
+                FieldValue::Points(points) => {
+                    for (index, point) in points.iter().enumerate() {
+                        match point {
+                            Some(point) => path.push(point_to_translation(*point)),
+                            None => problems.push(format!("Path point #{} is empty", index)),
                         }
                     }
-                    other => panic!("Unknown movement type: {:?}", other),
-                },
-                "GuardedAreaCorner1" => match &field.value {
-                    FieldValue::Point(value) => {
-                        area_corner_1 = value.map(|point| {
-                            ldtk_pixel_coords_to_translation_pivoted(
-                                point * layer_instance.grid_size,
-                                layer_instance.c_hei * layer_instance.grid_size,
-                                IVec2::new(entity_instance.width, entity_instance.height),
-                                entity_instance.pivot,
-                            )
-                        });
-                    }
-                    other => panic!("Unknown movement type: {:?}", other),
+                }
+                other => problems.push(format!("Path is not an array of points: {:?}", other)),
+            },
+            "Archetype" => match &field.value {
+                FieldValue::String(Some(value)) => archetype = value.clone(),
+                FieldValue::String(None) => {}
+                other => problems.push(format!("Archetype is not a string: {:?}", other)),
+            },
+            "Patrol_Mode" => match &field.value {
+                FieldValue::Enum(Some(value)) => match PatrolMode::from_ldtk_value(value) {
+                    Some(value) => patrol_mode = value,
+                    None => problems.push(format!("Unknown Patrol_Mode: {}", value)),
                 },
-                "GuardedAreaCorner2" => match &field.value {
-                    FieldValue::Point(value) => {
-                        area_corner_2 = value.map(|point| {
-                            ldtk_pixel_coords_to_translation_pivoted(
-                                point * layer_instance.grid_size,
-                                layer_instance.c_hei * layer_instance.grid_size,
-                                IVec2::new(entity_instance.width, entity_instance.height),
-                                entity_instance.pivot,
-                            )
-                        });
+                FieldValue::Enum(None) => {}
+                other => problems.push(format!("Patrol_Mode is not an enum: {:?}", other)),
+            },
+            "Wait_Times" => match &field.value {
+                FieldValue::Floats(values) => {
+                    wait_times = values.iter().map(|value| value.unwrap_or(0.)).collect();
+                }
+                FieldValue::Ints(values) => {
+                    wait_times = values
+                        .iter()
+                        .map(|value| value.unwrap_or(0) as f32)
+                        .collect();
+                }
+                other => problems.push(format!(
+                    "Wait_Times is not an array of numbers: {:?}",
+                    other
+                )),
+            },
+            "Facings" => match &field.value {
+                FieldValue::Enums(values) => {
+                    for (index, value) in values.iter().enumerate() {
+                        let facing = match value.as_deref() {
+                            Some("Up") => Some(Direction::Up),
+                            Some("Down") => Some(Direction::Down),
+                            Some("Left") => Some(Direction::Left),
+                            Some("Right") => Some(Direction::Right),
+                            Some(other) => {
+                                problems.push(format!("Facing #{} is unknown: {}", index, other));
+                                None
+                            }
+                            // Looks wherever it came from
+                            None => None,
+                        };
+
+                        facings.push(facing);
                     }
-                    other => panic!("Unknown movement type: {:?}", other),
+                }
+                other => problems.push(format!("Facings is not an array of enums: {:?}", other)),
+            },
+            "GuardedAreaCorner1" => match &field.value {
+                FieldValue::Point(value) => {
+                    area_corner_1 = value.map(point_to_translation);
+                }
+                other => problems.push(format!("GuardedAreaCorner1 is not a point: {:?}", other)),
+            },
+            "GuardedAreaCorner2" => match &field.value {
+                FieldValue::Point(value) => {
+                    area_corner_2 = value.map(point_to_translation);
+                }
+                other => problems.push(format!("GuardedAreaCorner2 is not a point: {:?}", other)),
+            },
+            "Vision_Half_Angle" => match number_field(&field.value) {
+                Ok(value) => overrides.vision_half_angle = value,
+                Err(other) => {
+                    problems.push(format!("Vision_Half_Angle is not a number: {:?}", other))
+                }
+            },
+            "Vision_Near_Range" => match number_field(&field.value) {
+                Ok(value) => overrides.vision_near_range = value,
+                Err(other) => {
+                    problems.push(format!("Vision_Near_Range is not a number: {:?}", other))
+                }
+            },
+            "Vision_Far_Range" => match number_field(&field.value) {
+                Ok(value) => overrides.vision_far_range = value,
+                Err(other) => {
+                    problems.push(format!("Vision_Far_Range is not a number: {:?}", other))
+                }
+            },
+            "Weapon" => match &field.value {
+                FieldValue::Enum(Some(value)) => match Weapon::from_ldtk_value(value) {
+                    Some(value) => overrides.weapon = Some(value),
+                    None => problems.push(format!("Unknown Weapon: {}", value)),
                 },
-                other => panic!("Unknown enemy field: {}", other),
-            }
+                // Not set means the enemy uses the archetype's weapon
+                FieldValue::Enum(None) => {}
+                other => problems.push(format!("Weapon is not an enum: {:?}", other)),
+            },
+            other => problems.push(format!("Unknown enemy field: {}", other)),
         }
+    }
 
-        let movement_type = match movement_type_str.as_str() {
-            "Static" => EnemyMovementType::Static { target: position },
-            "AlongPath" => {
-                println!("{:?}", path);
-                EnemyMovementType::AlongPath { path, current: 0 }
-            }
-            "GuardArea" => {
-                let corner_1 = area_corner_1.expect("Missing enemy area corner 1");
-                let corner_2 = area_corner_2.expect("Missing enemy area corner 2");
-
-                EnemyMovementType::GuardArea {
-                    area: Rect::from_corners(corner_1, corner_2),
-                    current: corner_1,
-                    wait_timer: Timer::from_seconds(3., TimerMode::Repeating),
+    let fallback = EnemyMovementType::Static { target: position };
+
+    let movement_type = match movement_type_str.as_deref() {
+        Some("Static") => fallback,
+        Some("AlongPath") => {
+            if path.len() > 1 {
+                // The enemy's own position comes first and the designer's points follow it
+                if wait_times.len() > path.len() - 1 {
+                    problems.push(format!(
+                        "There are {} Wait_Times, but only {} Path points",
+                        wait_times.len(),
+                        path.len() - 1
+                    ));
+                }
+
+                if facings.len() > path.len() - 1 {
+                    problems.push(format!(
+                        "There are {} Facings, but only {} Path points",
+                        facings.len(),
+                        path.len() - 1
+                    ));
+                }
+
+                if wait_times.iter().any(|wait| *wait < 0.) {
+                    problems.push("Wait_Times can't be negative".to_string());
+                }
+
+                let path = path
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, position)| Waypoint {
+                        position,
+                        wait: index
+                            .checked_sub(1)
+                            .and_then(|index| wait_times.get(index))
+                            .map(|wait| wait.max(0.))
+                            .unwrap_or(0.),
+                        facing: index
+                            .checked_sub(1)
+                            .and_then(|index| facings.get(index).copied().flatten()),
+                    })
+                    .collect();
+
+                EnemyMovementType::AlongPath {
+                    path,
+                    current: 0,
+                    mode: patrol_mode,
+                    reverse: false,
+                    wait_timer: Timer::from_seconds(0., TimerMode::Once),
                 }
+            } else {
+                problems.push("AlongPath enemy has no Path points".to_string());
+                fallback
             }
-            other => panic!("Unknown enemy movement type: {}", other),
-        };
+        }
+        Some("GuardArea") => match (area_corner_1, area_corner_2) {
+            (Some(corner_1), Some(corner_2)) => EnemyMovementType::GuardArea {
+                area: Rect::from_corners(corner_1, corner_2),
+                current: corner_1,
+                wait_timer: Timer::from_seconds(3., TimerMode::Repeating),
+            },
+            _ => {
+                if area_corner_1.is_none() {
+                    problems.push("Missing GuardedAreaCorner1".to_string());
+                }
 
-        Self {
-            movement_type,
-            ..default()
+                if area_corner_2.is_none() {
+                    problems.push("Missing GuardedAreaCorner2".to_string());
+                }
+
+                fallback
+            }
+        },
+        Some(other) => {
+            problems.push(format!("Unknown Enemy_Type: {}", other));
+            fallback
         }
+        None => {
+            problems.push("Enemy_Type is not set".to_string());
+            fallback
+        }
+    };
+
+    // The archetype isn't loaded yet, so the overrides get checked against the default vision
+    let vision_cone = overrides.vision_cone(VisionCone::default());
+
+    if vision_cone.half_angle <= 0. || vision_cone.half_angle > 180. {
+        problems.push(format!(
+            "Vision_Half_Angle has to be between 0 and 180 degrees, got {}",
+            vision_cone.half_angle
+        ));
+        overrides.vision_half_angle = None;
+    }
+
+    if vision_cone.near_range < 0. || vision_cone.near_range > vision_cone.far_range {
+        problems.push(format!(
+            "Vision ranges have to satisfy 0 <= Vision_Near_Range <= Vision_Far_Range, got {} and {}",
+            vision_cone.near_range, vision_cone.far_range
+        ));
+        overrides.vision_near_range = None;
+        overrides.vision_far_range = None;
     }
+
+    (
+        EnemyFields {
+            movement_type,
+            archetype,
+            overrides,
+        },
+        problems,
+    )
 }
 
 fn adjust_enemy_scale(mut enemy_query: Query<&mut Transform, Added<EnemyState>>) {
@@ -217,18 +475,16 @@ fn adjust_enemy_scale(mut enemy_query: Query<&mut Transform, Added<EnemyState>>)
 
 fn update_sprites(
     mut enemy_query: Query<
-        (&mut Handle<TextureAtlas>, &Direction),
-        (With<EnemyState>, Changed<Direction>),
+        (&mut Handle<TextureAtlas>, &Direction, &EnemySpriteSet),
+        (
+            With<EnemyState>,
+            Or<(Changed<Direction>, Changed<EnemySpriteSet>)>,
+        ),
     >,
     textures: Res<TextureAssets>,
 ) {
-    for (mut texture, direction) in enemy_query.iter_mut() {
-        *texture = match direction {
-            Direction::Up => textures.enemy_up.clone(),
-            Direction::Down => textures.enemy_down.clone(),
-            Direction::Left => textures.enemy_left.clone(),
-            Direction::Right => textures.enemy_right.clone(),
-        }
+    for (mut texture, direction, sprite_set) in enemy_query.iter_mut() {
+        *texture = sprite_set.texture_atlas(&textures, *direction);
     }
 }
 
@@ -242,12 +498,12 @@ fn update_z_index(mut enemy_query: Query<(&mut Transform, &Direction), With<Enem
 }
 
 fn handle_stunned_enemies(
-    mut enemy_query: Query<(&mut EnemyState, &mut AnimationManager)>,
+    mut enemy_query: Query<(&mut EnemyState, &mut AnimationManager, &StunResistance)>,
     time: Res<Time>,
 ) {
-    for (mut enemy_state, mut animation_manager) in enemy_query.iter_mut() {
+    for (mut enemy_state, mut animation_manager, stun_resistance) in enemy_query.iter_mut() {
         if let EnemyState::Stun { timer } = enemy_state.as_mut() {
-            timer.tick(time.delta());
+            timer.tick(time.delta().mul_f32(stun_resistance.recovery_speed()));
 
             animation_manager
                 .set_state("stun".to_string(), true)
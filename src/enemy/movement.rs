@@ -1,31 +1,146 @@
+use std::{
+    f32::consts::{PI, TAU},
+    time::Duration,
+};
+
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::{Collider, QueryFilter, RapierContext};
 use bevy_spritesheet_animation::animation_manager::AnimationManager;
 use navmesh::{NavPathMode, NavQuery, NavVec3};
+use rand::Rng;
 
 use crate::{
-    level::WorldNavMesh,
+    level::{
+        door::NavMeshAreaBlocked,
+        nav_agent::{nav_mesh_for_agent, AgentClass, AgentLevel, NavMeshAgents},
+        WorldNavMesh,
+    },
     player::Player,
+    rng::GameRng,
     unit::{Direction, Euler, Movement},
     GameState,
 };
 
-use super::EnemyState;
+use super::{avoidance::Separation, EnemyState, ENEMY_COLLIDER_HEIGHT, ENEMY_COLLIDER_WIDTH};
 
 pub struct EnemyMovementPlugin;
 
 impl Plugin for EnemyMovementPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<EnemyMovementTarget>().add_systems(
-            (enemy_movement, enemy_guard_area_timer, avoid_overlap)
-                .in_set(OnUpdate(GameState::Playing)),
-        );
+        app.register_type::<EnemyMovementTarget>()
+            .register_type::<ApproachOffset>()
+            .init_resource::<EnemySearchSettings>()
+            .init_resource::<EnemyRepathSettings>()
+            .add_systems(
+                (
+                    assign_approach_offsets.before(enemy_movement),
+                    enemy_movement,
+                    enemy_guard_area_timer,
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
     }
 }
 
+/// How enemies look for a player they lost sight of
+#[derive(Resource, Clone, Debug)]
+pub struct EnemySearchSettings {
+    /// How long an enemy looks around the last known position before going back to its route, in seconds
+    pub duration: f32,
+    /// How far from the last known position the enemy looks
+    pub radius: f32,
+}
+
+impl Default for EnemySearchSettings {
+    fn default() -> Self {
+        Self {
+            duration: 8.,
+            radius: 250.,
+        }
+    }
+}
+
+/// How often enemies chasing the player plan their way again
+#[derive(Resource, Clone, Debug)]
+pub struct EnemyRepathSettings {
+    /// The path gets planned again right away once the player gets this far from its end
+    pub distance: f32,
+    /// Smaller moves of the player only get caught up with this often, in seconds
+    pub interval: f32,
+}
+
+impl Default for EnemyRepathSettings {
+    fn default() -> Self {
+        Self {
+            distance: 48.,
+            interval: 0.5,
+        }
+    }
+}
+
+/// Part of the enemy's size that has to fit past the walls when cutting corners of its path
+const SMOOTHING_CLEARANCE: f32 = 0.8;
+
+/// How close an enemy being pushed around by the others has to get to a path point
+const CROWDED_ARRIVAL_DISTANCE: f32 = 5.;
+
+/// How far apart the alerted enemies stand from each other around the player
+const APPROACH_SPACING: f32 = ENEMY_COLLIDER_WIDTH * 1.25;
+
+/// How far from the player the given number of alerted enemies spread out, far enough
+/// for them to keep their spacing and not to end up on top of the player.
+fn approach_radius(count: usize) -> f32 {
+    // Neighbours on a circle are 2 * radius * sin(PI / count) apart
+    let radius = APPROACH_SPACING / (2. * (PI / count as f32).sin());
+
+    radius.max(ENEMY_COLLIDER_WIDTH)
+}
+
+/// Where around the player an alerted enemy heads to, so the enemies chasing the player
+/// surround them instead of all running to the same spot
+#[derive(Reflect, Component, Clone, Copy, Default, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct ApproachOffset(pub Vec2);
+
 #[derive(Reflect, Component, Clone, Default, Debug, PartialEq)]
 #[reflect(Component)]
 pub struct EnemyMovementTarget {
     pub path: Vec<Vec2>,
+    /// Seconds since the enemy last planned its way to the player
+    pub since_repath: f32,
+}
+
+/// What an enemy walking along a path does once it gets to the end of it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PatrolMode {
+    /// Goes straight back to the first point
+    #[default]
+    Loop,
+    /// Walks the path back the way it came
+    PingPong,
+    /// Stays at the last point
+    Once,
+}
+
+impl PatrolMode {
+    pub fn from_ldtk_value(value: &str) -> Option<Self> {
+        match value {
+            "Loop" => Some(PatrolMode::Loop),
+            "PingPong" => Some(PatrolMode::PingPong),
+            "Once" => Some(PatrolMode::Once),
+            _ => None,
+        }
+    }
+}
+
+/// A point on the path of a patrolling enemy
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Waypoint {
+    pub position: Vec2,
+    /// How long the enemy stays at the point before moving on, in seconds
+    pub wait: f32,
+    /// Where the enemy looks while it waits
+    pub facing: Option<Direction>,
 }
 
 #[derive(Component, Clone, Debug)]
@@ -34,8 +149,13 @@ pub enum EnemyMovementType {
         target: Vec2,
     },
     AlongPath {
-        path: Vec<Vec2>,
+        path: Vec<Waypoint>,
+        /// The waypoint the enemy is heading to, or waiting at
         current: usize,
+        mode: PatrolMode,
+        /// Whether a ping-ponging enemy is on its way back
+        reverse: bool,
+        wait_timer: Timer,
     },
     GuardArea {
         area: Rect,
@@ -45,11 +165,50 @@ pub enum EnemyMovementType {
 }
 
 impl EnemyMovementType {
-    fn move_to_next(&mut self) {
+    /// Points placed by the level designer that the enemy is going to walk to.
+    pub fn waypoints(&self) -> Vec<Vec2> {
+        match self {
+            EnemyMovementType::Static { target } => vec![*target],
+            EnemyMovementType::AlongPath { path, .. } => {
+                path.iter().map(|waypoint| waypoint.position).collect()
+            }
+            EnemyMovementType::GuardArea {
+                area,
+                current: _,
+                wait_timer: _,
+            } => vec![area.min, area.max],
+        }
+    }
+
+    fn move_to_next(&mut self, rng: &mut impl Rng) {
         match self {
             EnemyMovementType::Static { target: _ } => (),
-            EnemyMovementType::AlongPath { path, current } => {
-                *current = (*current + 1) % path.len();
+            EnemyMovementType::AlongPath {
+                path,
+                current,
+                mode,
+                reverse,
+                wait_timer,
+            } => {
+                *current = match mode {
+                    PatrolMode::Loop => (*current + 1) % path.len(),
+                    PatrolMode::Once => (*current + 1).min(path.len() - 1),
+                    PatrolMode::PingPong => {
+                        if *current == 0 {
+                            *reverse = false;
+                        } else if *current == path.len() - 1 {
+                            *reverse = true;
+                        }
+
+                        if *reverse {
+                            *current - 1
+                        } else {
+                            *current + 1
+                        }
+                    }
+                };
+
+                *wait_timer = Timer::from_seconds(path[*current].wait, TimerMode::Once);
             }
             EnemyMovementType::GuardArea {
                 area,
@@ -58,8 +217,8 @@ impl EnemyMovementType {
             } => {
                 let random_point = area.center()
                     + Vec2::new(
-                        (rand::random::<f32>() - 0.5) * area.width(),
-                        (rand::random::<f32>() - 0.5) * area.height(),
+                        (rng.gen::<f32>() - 0.5) * area.width(),
+                        (rng.gen::<f32>() - 0.5) * area.height(),
                     );
 
                 *current = random_point;
@@ -70,7 +229,7 @@ impl EnemyMovementType {
     fn target(&self) -> Option<Vec2> {
         match self {
             EnemyMovementType::Static { target } => Some(*target),
-            EnemyMovementType::AlongPath { path, current } => Some(path[*current]),
+            EnemyMovementType::AlongPath { path, current, .. } => Some(path[*current].position),
             EnemyMovementType::GuardArea {
                 area: _,
                 current,
@@ -84,75 +243,197 @@ impl EnemyMovementType {
             }
         }
     }
+
+    /// Lets the time pass for an enemy standing at its current waypoint.
+    ///
+    /// # Returns
+    /// Where the enemy should be looking if it is still waiting there,
+    /// or `None` once it's time to move on.
+    fn wait(&mut self, delta: Duration, direction: Direction) -> Option<Direction> {
+        match self {
+            EnemyMovementType::AlongPath {
+                path,
+                current,
+                mode,
+                wait_timer,
+                ..
+            } => {
+                let waypoint = path[*current];
+                let at_the_end = *mode == PatrolMode::Once && *current == path.len() - 1;
+
+                if wait_timer.tick(delta).finished() && !at_the_end {
+                    None
+                } else {
+                    Some(waypoint.facing.unwrap_or(direction))
+                }
+            }
+            _ => None,
+        }
+    }
 }
 
 pub fn enemy_movement(
     mut enemy_query: Query<(
-        &EnemyState,
+        &mut EnemyState,
         &mut EnemyMovementTarget,
         &mut EnemyMovementType,
         &mut AnimationManager,
         &Movement,
         &mut Direction,
         &mut Transform,
+        &AgentClass,
+        &AgentLevel,
+        &ApproachOffset,
+        &Separation,
     )>,
     player_query: Query<&Transform, (With<Player>, Without<EnemyState>)>,
     nav_mesh_query: Query<&WorldNavMesh>,
+    mut area_blocked: EventReader<NavMeshAreaBlocked>,
+    rapier_context: Res<RapierContext>,
+    agents: Res<NavMeshAgents>,
+    search_settings: Res<EnemySearchSettings>,
+    repath_settings: Res<EnemyRepathSettings>,
     time: Res<Time>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let nav_mesh = if let Ok(nav_mesh) = nav_mesh_query.get_single() {
-        nav_mesh
-    } else {
-        return;
-    };
+    let blocked_areas = area_blocked
+        .iter()
+        .map(|event| event.area)
+        .collect::<Vec<_>>();
 
     let player_transform = player_query.single();
 
     for (
-        enemy_state,
+        mut enemy_state,
         mut enemy_movement_target,
         mut enemy_movement_type,
         mut enemy_animation_manager,
         enemy_movement,
         mut enemy_direction,
         mut enemy_transform,
+        agent_class,
+        agent_level,
+        approach_offset,
+        separation,
     ) in enemy_query.iter_mut()
     {
-        let new_target = match *enemy_state {
+        let nav_mesh = if let Some(nav_mesh) =
+            nav_mesh_for_agent(&nav_mesh_query, agent_level, *agent_class)
+        {
+            nav_mesh
+        } else {
+            continue;
+        };
+
+        let agent_size = agents
+            .size_of(*agent_class)
+            .unwrap_or(Vec2::new(ENEMY_COLLIDER_WIDTH, ENEMY_COLLIDER_HEIGHT));
+
+        let new_target = match enemy_state.as_mut() {
             EnemyState::Idle => {
                 if enemy_movement_target.path.is_empty() {
-                    enemy_movement_type.move_to_next();
-                    let target = enemy_movement_type
-                        .target()
-                        .unwrap_or(enemy_transform.translation.truncate());
+                    if let Some(facing) = enemy_movement_type.wait(time.delta(), *enemy_direction) {
+                        enemy_direction.set_if_neq(facing);
 
-                    Some(target)
+                        None
+                    } else {
+                        enemy_movement_type.move_to_next(&mut game_rng.ai);
+                        let target = enemy_movement_type
+                            .target()
+                            .unwrap_or(enemy_transform.translation.truncate());
+
+                        Some(target)
+                    }
                 } else {
                     None
                 }
             }
             EnemyState::Alert { target } => {
+                let target = *target + approach_offset.0;
+
+                enemy_movement_target.since_repath += time.delta_seconds();
+
                 if let Some(target) =
                     nav_mesh.closest_point(NavVec3::new(target.x, target.y, 0.), NavQuery::Closest)
                 {
                     let target = Vec2::new(target.x, target.y);
 
-                    if let Some(path_target) = enemy_movement_target.path.last() {
-                        if *path_target != target {
-                            Some(target)
-                        } else {
-                            None
-                        }
+                    // Planning the way every time the player moves a bit is too expensive,
+                    // the enemy keeps following the old path in the meantime
+                    let repath = if let Some(path_target) = enemy_movement_target.path.last() {
+                        let drift = path_target.distance(target);
+
+                        drift > repath_settings.distance
+                            || (drift > 0.
+                                && enemy_movement_target.since_repath >= repath_settings.interval)
                     } else {
+                        true
+                    };
+
+                    if repath {
+                        enemy_movement_target.since_repath = 0.;
+
                         Some(target)
+                    } else {
+                        None
                     }
                 } else {
                     None
                 }
             }
+            EnemyState::Search { last_known, timer } => {
+                let enemy_position = enemy_transform.translation.truncate();
+
+                let near_last_known =
+                    enemy_position.distance(*last_known) <= search_settings.radius;
+
+                // The search only starts counting down once the enemy gets to the place,
+                // or when it can't find a way there
+                if near_last_known || enemy_movement_target.path.is_empty() {
+                    timer.tick(time.delta());
+                }
+
+                if timer.finished() {
+                    *enemy_state = EnemyState::Idle;
+                    enemy_movement_target.path.clear();
+
+                    // Back to where it was heading before
+                    Some(enemy_movement_type.target().unwrap_or(enemy_position))
+                } else if enemy_movement_target.path.is_empty() && !near_last_known {
+                    Some(*last_known)
+                } else if enemy_movement_target.path.is_empty() {
+                    let offset = Vec2::from_angle(game_rng.ai.gen::<f32>() * TAU)
+                        * game_rng.ai.gen::<f32>().sqrt()
+                        * search_settings.radius;
+
+                    Some(*last_known + offset)
+                } else {
+                    None
+                }
+            }
             EnemyState::Stun { timer: _ } => continue,
         };
 
+        // Something closed in the way, so the enemy has to find a way around it
+        let new_target = new_target.or_else(|| {
+            let blocked = blocked_areas.iter().any(|area| {
+                path_crosses_area(
+                    enemy_transform.translation.truncate(),
+                    &enemy_movement_target.path,
+                    *area,
+                )
+            });
+
+            if blocked {
+                let target = enemy_movement_target.path.last().copied();
+                enemy_movement_target.path.clear();
+
+                target
+            } else {
+                None
+            }
+        });
+
         if let Some(target) = new_target {
             if let Some(target) =
                 nav_mesh.closest_point(NavVec3::new(target.x, target.y, 0.), NavQuery::Closest)
@@ -167,68 +448,236 @@ pub fn enemy_movement(
                     NavQuery::Accuracy,
                     NavPathMode::Accuracy,
                 ) {
-                    enemy_movement_target.path = path
+                    let path = path
                         .iter()
                         .map(|point| Vec2::new(point.x, point.y))
                         .skip(1)
-                        .collect();
+                        .collect::<Vec<_>>();
+
+                    let clearance = Collider::cuboid(
+                        agent_size.x / 2. * SMOOTHING_CLEARANCE,
+                        agent_size.y / 2. * SMOOTHING_CLEARANCE,
+                    );
+
+                    // Only walls and doors are in the way, everyone else can be walked around
+                    let is_clear = |from: Vec2, to: Vec2| {
+                        rapier_context
+                            .cast_shape(
+                                from,
+                                0.,
+                                to - from,
+                                &clearance,
+                                1.,
+                                QueryFilter::only_fixed().exclude_sensors(),
+                            )
+                            .is_none()
+                    };
+
+                    enemy_movement_target.path =
+                        string_pull(enemy_transform.translation.truncate(), &path, is_clear);
                 }
             } else {
                 println!("Closest point to ({}, {}) not found", target.x, target.y);
             }
         }
 
+        let enemy_position = enemy_transform.translation.truncate();
+
+        // An enemy right next to the player stops following its path, but still makes room
+        // for the others closing in
         let distance = player_transform
             .translation
             .truncate()
-            .distance(enemy_transform.translation.truncate());
+            .distance(enemy_position);
+        let holding_position = distance < 15.0 && matches!(*enemy_state, EnemyState::Alert { .. });
 
-        if distance < 15.0 && matches!(*enemy_state, EnemyState::Alert { .. }) {
-            continue;
-        }
+        let target = if holding_position {
+            None
+        } else {
+            enemy_movement_target.path.get(0).copied()
+        };
 
-        if let Some(target) = enemy_movement_target.path.get(0) {
-            let movement_vector = *target - enemy_transform.translation.truncate();
+        let speed = if matches!(*enemy_state, EnemyState::Idle | EnemyState::Search { .. }) {
+            enemy_movement.speed
+        } else {
+            enemy_movement.running_speed
+        };
 
-            let speed = if matches!(*enemy_state, EnemyState::Idle) {
-                enemy_movement.speed
+        let max_step = speed * time.delta_seconds();
+
+        let path_step = if let Some(target) = target {
+            (target - enemy_position).clamp_length_max(max_step)
+        } else {
+            Vec2::ZERO
+        };
+
+        // Keeping out of the others' way on top of following the path, as long as that doesn't
+        // push the enemy off its nav mesh and into a wall
+        let movement_vector = if separation.0 == Vec2::ZERO {
+            path_step
+        } else {
+            let pushed_step =
+                (path_step + separation.0 * time.delta_seconds()).clamp_length_max(max_step);
+            let pushed = enemy_position + pushed_step;
+
+            let stays_on_nav_mesh = nav_mesh
+                .closest_point(NavVec3::new(pushed.x, pushed.y, 0.), NavQuery::Accuracy)
+                .map_or(false, |closest| {
+                    Vec2::new(closest.x, closest.y).distance(pushed) < 1.
+                });
+
+            if stays_on_nav_mesh {
+                pushed_step
             } else {
-                enemy_movement.running_speed
-            };
+                path_step
+            }
+        };
 
-            let calc_movement_vector =
-                movement_vector.normalize_or_zero() * speed * time.delta_seconds();
+        enemy_transform.translation += movement_vector.extend(0.0);
 
-            let movement_vector = if calc_movement_vector.length() > movement_vector.length() {
+        if movement_vector != Vec2::ZERO {
+            // Facing where it's headed, not where it's being pushed
+            let facing = if path_step == Vec2::ZERO {
                 movement_vector
             } else {
-                calc_movement_vector
+                path_step
             };
 
-            enemy_transform.translation += movement_vector.extend(0.0);
-
-            if movement_vector != Vec2::ZERO {
-                let movement_angle =
-                    Euler::from_radians(movement_vector.angle_between(Vec2::new(0.0, 1.0)));
-                enemy_direction.set_if_neq(Direction::from(movement_angle));
+            let movement_angle = Euler::from_radians(facing.angle_between(Vec2::new(0.0, 1.0)));
+            enemy_direction.set_if_neq(Direction::from(movement_angle));
 
-                enemy_animation_manager
-                    .set_state("walk".to_string(), true)
-                    .unwrap();
-            } else {
-                enemy_animation_manager
-                    .set_state("walk".to_string(), false)
-                    .unwrap();
-            }
-
-            if (*target - enemy_transform.translation.truncate()).length() < 0.1 {
-                enemy_movement_target.path.remove(0);
-            }
+            enemy_animation_manager
+                .set_state("walk".to_string(), true)
+                .unwrap();
         } else {
             enemy_animation_manager
                 .set_state("walk".to_string(), false)
                 .unwrap();
         }
+
+        // Someone pushing the enemy around could keep it from ever getting exactly to the point
+        let arrival_distance = if separation.0 == Vec2::ZERO {
+            0.1
+        } else {
+            CROWDED_ARRIVAL_DISTANCE
+        };
+
+        if let Some(target) = target {
+            if (target - enemy_transform.translation.truncate()).length() < arrival_distance {
+                enemy_movement_target.path.remove(0);
+            }
+        }
+    }
+}
+
+/// Cuts the corners of a nav mesh path, going straight to the furthest point
+/// that can be walked to in a line.
+fn string_pull(start: Vec2, path: &[Vec2], is_clear: impl Fn(Vec2, Vec2) -> bool) -> Vec<Vec2> {
+    let mut smoothed = Vec::new();
+    let mut from = start;
+    let mut index = 0;
+
+    while index < path.len() {
+        // The next point can always be reached, the nav mesh already made sure of that
+        let furthest = (index + 1..path.len())
+            .rev()
+            .find(|candidate| is_clear(from, path[*candidate]))
+            .unwrap_or(index);
+
+        smoothed.push(path[furthest]);
+
+        from = path[furthest];
+        index = furthest + 1;
+    }
+
+    smoothed
+}
+
+/// Checks whether walking from `start` along the path goes through the area.
+fn path_crosses_area(start: Vec2, path: &[Vec2], area: Rect) -> bool {
+    let mut from = start;
+
+    for to in path {
+        if segment_crosses_rect(from, *to, area) {
+            return true;
+        }
+
+        from = *to;
+    }
+
+    false
+}
+
+fn segment_crosses_rect(from: Vec2, to: Vec2, rect: Rect) -> bool {
+    let direction = to - from;
+
+    // Clipping the segment against the rect one axis at a time
+    let mut t_min = 0.0_f32;
+    let mut t_max = 1.0_f32;
+
+    for axis in 0..2 {
+        if direction[axis].abs() < f32::EPSILON {
+            if from[axis] < rect.min[axis] || from[axis] > rect.max[axis] {
+                return false;
+            }
+
+            continue;
+        }
+
+        let t1 = (rect.min[axis] - from[axis]) / direction[axis];
+        let t2 = (rect.max[axis] - from[axis]) / direction[axis];
+
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+
+        if t_min > t_max {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Spreads the enemies chasing the player evenly around them. The slots are handed out
+/// in the order the enemies already stand around the player, so their paths don't cross.
+fn assign_approach_offsets(mut enemy_query: Query<(&EnemyState, &Transform, &mut ApproachOffset)>) {
+    let mut chasing = enemy_query
+        .iter_mut()
+        .filter_map(|(enemy_state, transform, approach_offset)| {
+            if let EnemyState::Alert { target } = enemy_state {
+                Some((*target, transform.translation.truncate(), approach_offset))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if chasing.is_empty() {
+        return;
+    }
+
+    // There's only one player, so all the targets are around the same spot
+    let center = chasing.iter().map(|(target, _, _)| *target).sum::<Vec2>() / chasing.len() as f32;
+
+    let bearing = |position: Vec2| {
+        let direction = position - center;
+        direction.y.atan2(direction.x)
+    };
+
+    chasing.sort_by(|(_, a, _), (_, b, _)| bearing(*a).total_cmp(&bearing(*b)));
+
+    let first_bearing = bearing(chasing[0].1);
+    let count = chasing.len();
+
+    for (index, (_, _, approach_offset)) in chasing.iter_mut().enumerate() {
+        let offset = if count == 1 {
+            Vec2::ZERO
+        } else {
+            Vec2::from_angle(first_bearing + TAU * index as f32 / count as f32)
+                * approach_radius(count)
+        };
+
+        approach_offset.set_if_neq(ApproachOffset(offset));
     }
 }
 
@@ -257,53 +706,3 @@ fn enemy_guard_area_timer(
         }
     }
 }
-
-fn avoid_overlap(
-    mut enemies: Query<(&mut Transform, &EnemyState), With<EnemyState>>,
-    nav_mesh_query: Query<&WorldNavMesh>,
-    time: Res<Time>,
-) {
-    let nav_mesh = if let Ok(nav_mesh) = nav_mesh_query.get_single() {
-        nav_mesh
-    } else {
-        return;
-    };
-
-    let mut combinations = enemies.iter_combinations_mut();
-
-    while let Some([(mut transform1, state1), (mut transform2, state2)]) = combinations.fetch_next()
-    {
-        if matches!(state1, EnemyState::Stun { .. }) && matches!(state2, EnemyState::Stun { .. }) {
-            continue;
-        }
-
-        let vector = transform1.translation.truncate() - transform2.translation.truncate();
-
-        let distance = vector.length();
-
-        if distance < 25. {
-            let normalized = if distance == 0. {
-                Vec2::new(1., 0.)
-            } else {
-                vector / distance
-            };
-
-            let target1 = transform1.translation.truncate()
-                + normalized / distance * 500. * time.delta_seconds();
-            let target2 = transform2.translation.truncate()
-                - normalized / distance * 500. * time.delta_seconds();
-
-            if let Some(target) =
-                nav_mesh.closest_point(NavVec3::new(target1.x, target1.y, 0.), NavQuery::Accuracy)
-            {
-                transform1.translation = Vec3::new(target.x, target.y, transform1.translation.z);
-            }
-
-            if let Some(target) =
-                nav_mesh.closest_point(NavVec3::new(target2.x, target2.y, 0.), NavQuery::Accuracy)
-            {
-                transform2.translation = Vec3::new(target.x, target.y, transform2.translation.z);
-            }
-        }
-    }
-}
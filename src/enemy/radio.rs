@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::{unit::Health, GameState};
+
+use super::{sight::Suspicion, EnemyState};
+
+pub struct EnemyRadioPlugin;
+
+impl Plugin for EnemyRadioPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AlertBroadcast>()
+            .init_resource::<RadioSettings>()
+            .add_event::<EnemyAlerted>()
+            .add_systems(
+                (
+                    start_alert_broadcasts,
+                    broadcast_alerts.after(start_alert_broadcasts),
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+/// How enemies tell each other about the player
+#[derive(Resource, Clone, Debug)]
+pub struct RadioSettings {
+    /// How far away the other enemies hear the radio
+    pub range: f32,
+    /// How long it takes the enemy to call it in after spotting the player, in seconds
+    pub delay: f32,
+}
+
+impl Default for RadioSettings {
+    fn default() -> Self {
+        Self {
+            range: 600.,
+            delay: 1.5,
+        }
+    }
+}
+
+/// Sent when an enemy spots the player on its own
+#[derive(Clone, Copy, Debug)]
+pub struct EnemyAlerted {
+    pub enemy: Entity,
+}
+
+/// The enemy is about to tell the others where the player is
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct AlertBroadcast {
+    pub timer: Timer,
+}
+
+fn start_alert_broadcasts(
+    mut commands: Commands,
+    enemy_query: Query<&Health, (With<EnemyState>, Without<AlertBroadcast>)>,
+    mut enemy_alerted: EventReader<EnemyAlerted>,
+    radio_settings: Res<RadioSettings>,
+) {
+    for event in enemy_alerted.iter() {
+        // Enemies already calling it in are left alone, and so are the ones that are gone
+        // or get knocked out this frame, inserting into them would panic once they're despawned
+        match enemy_query.get(event.enemy) {
+            Ok(health) if health.get_health() > 0. => {}
+            _ => continue,
+        }
+
+        commands.entity(event.enemy).insert(AlertBroadcast {
+            timer: Timer::from_seconds(radio_settings.delay, TimerMode::Once),
+        });
+    }
+}
+
+fn broadcast_alerts(
+    mut commands: Commands,
+    mut broadcast_query: Query<(Entity, &mut AlertBroadcast)>,
+    mut enemy_query: Query<(Entity, &mut EnemyState, &mut Suspicion, &Transform)>,
+    radio_settings: Res<RadioSettings>,
+    time: Res<Time>,
+) {
+    for (sender_entity, mut broadcast) in broadcast_query.iter_mut() {
+        if !broadcast.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        commands.entity(sender_entity).remove::<AlertBroadcast>();
+
+        // Only an enemy that's still chasing the player knows where they are
+        let (target, sender_position) =
+            if let Ok((_, enemy_state, _, transform)) = enemy_query.get(sender_entity) {
+                if let EnemyState::Alert { target } = *enemy_state {
+                    (target, transform.translation.truncate())
+                } else {
+                    continue;
+                }
+            } else {
+                continue;
+            };
+
+        for (ally_entity, mut ally_state, mut ally_suspicion, ally_transform) in
+            enemy_query.iter_mut()
+        {
+            if ally_entity == sender_entity
+                || matches!(
+                    *ally_state,
+                    EnemyState::Alert { .. } | EnemyState::Stun { .. }
+                )
+                || ally_transform
+                    .translation
+                    .truncate()
+                    .distance(sender_position)
+                    > radio_settings.range
+            {
+                continue;
+            }
+
+            *ally_state = EnemyState::Alert { target };
+            **ally_suspicion = 1.;
+        }
+    }
+}
@@ -2,79 +2,188 @@ use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioControl};
 use bevy_rapier2d::prelude::{QueryFilter, RapierContext};
 
-use crate::{
-    player::Player,
-    unit::{Direction, Euler},
-    GameState, loading::AudioAssets,
+use crate::{loading::AudioAssets, player::Player, unit::Direction, GameState};
+
+use super::{
+    super::player::effect::Invisibility,
+    movement::{enemy_movement, EnemySearchSettings},
+    radio::EnemyAlerted,
+    EnemyState,
 };
 
-use super::{super::player::effect::Invisibility, movement::enemy_movement, EnemyState};
+/// How fast the suspicion fills up when the player is right in front of the enemy
+const SUSPICION_GAIN_PER_SECOND: f32 = 4.;
+/// How fast the suspicion goes away when the enemy doesn't see the player
+const SUSPICION_DECAY_PER_SECOND: f32 = 0.25;
 
 pub struct EnemySightPlugin;
 
 impl Plugin for EnemySightPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(
-            see_player
-                .after(enemy_movement)
-                .in_set(OnUpdate(GameState::Playing)),
-        );
+        app.register_type::<VisionCone>()
+            .register_type::<Suspicion>()
+            .add_system(
+                see_player
+                    .after(enemy_movement)
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+/// The area in front of the enemy where it can see the player
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct VisionCone {
+    /// Angle between the direction the enemy is looking in and the edge of the cone, in degrees
+    pub half_angle: f32,
+    /// Up to this distance the player gets noticed at full speed
+    pub near_range: f32,
+    /// The furthest the enemy can see
+    pub far_range: f32,
+}
+
+impl Default for VisionCone {
+    fn default() -> Self {
+        Self {
+            half_angle: 45.,
+            near_range: 150.,
+            far_range: 500.,
+        }
+    }
+}
+
+impl VisionCone {
+    /// How well something is seen at the given distance and angle (in radians)
+    /// from the direction the enemy is looking in.
+    ///
+    /// # Returns
+    /// 1 right in front of the enemy within the near range, falling off towards
+    /// the far range and the edges of the cone, and 0 outside of the cone.
+    pub fn visibility(&self, distance: f32, angle: f32) -> f32 {
+        let half_angle = self.half_angle.to_radians();
+
+        if distance > self.far_range || angle > half_angle {
+            return 0.;
+        }
+
+        let distance_factor = if distance <= self.near_range {
+            1.
+        } else {
+            1. - (distance - self.near_range) / (self.far_range - self.near_range) * 0.75
+        };
+
+        let angle_factor = 1. - angle / half_angle * 0.5;
+
+        distance_factor * angle_factor
     }
 }
 
+/// How close the enemy is to noticing the player, from 0 to 1
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Reflect, Deref, DerefMut)]
+#[reflect(Component)]
+pub struct Suspicion(pub f32);
+
+/// Once alerted, the enemy keeps track of the player all around itself,
+/// otherwise it only sees what's in its vision cone.
+pub fn sees_all_around(enemy_state: &EnemyState) -> bool {
+    matches!(enemy_state, EnemyState::Alert { .. })
+}
+
 pub fn see_player(
-    mut enemy_query: Query<(&mut EnemyState, &Transform, &Direction)>,
+    mut enemy_query: Query<(
+        Entity,
+        &mut EnemyState,
+        &mut Suspicion,
+        &VisionCone,
+        &Transform,
+        &Direction,
+    )>,
     player_query: Query<(Entity, &Transform, Option<&Invisibility>), With<Player>>,
     rapier_context: Res<RapierContext>,
     audio: Res<Audio>,
     audio_assets: Res<AudioAssets>,
+    search_settings: Res<EnemySearchSettings>,
+    time: Res<Time>,
+    mut enemy_alerted: EventWriter<EnemyAlerted>,
 ) {
     let (player_entity, player_transform, player_invisibility) = player_query.single();
 
-    for (mut enemy_state, enemy_transform, enemy_direction) in enemy_query.iter_mut() {
+    for (
+        enemy_entity,
+        mut enemy_state,
+        mut suspicion,
+        vision_cone,
+        enemy_transform,
+        enemy_direction,
+    ) in enemy_query.iter_mut()
+    {
         if matches!(*enemy_state, EnemyState::Stun { .. }) {
             continue;
         }
 
+        let alerted = matches!(*enemy_state, EnemyState::Alert { .. });
+        let all_around = sees_all_around(&enemy_state);
+
         let to_player_vector =
             (player_transform.translation - enemy_transform.translation).truncate();
 
-        let distance = player_transform
-            .translation
-            .truncate()
-            .distance(enemy_transform.translation.truncate());
+        let distance = to_player_vector.length();
 
-        let angle = Euler::from_radians(to_player_vector.angle_between(Vec2::new(0., 1.)));
+        let angle = if all_around || distance == 0. {
+            0.
+        } else {
+            enemy_direction
+                .as_vec2()
+                .angle_between(to_player_vector)
+                .abs()
+        };
 
-        let enemy_sight = 500.0;
+        let visibility = if player_invisibility.is_some() {
+            0.
+        } else {
+            vision_cone.visibility(distance, angle)
+        };
 
-        let see_player = distance < 15.
-            || (if matches!(*enemy_state.as_ref(), EnemyState::Idle) {
-                Direction::from(angle) == *enemy_direction
-            } else {
-                true
-            } && if let Some((entity, _)) = rapier_context.cast_ray(
+        let in_line_of_sight = visibility > 0.
+            && if let Some((entity, _)) = rapier_context.cast_ray(
                 enemy_transform.translation.truncate(),
-                to_player_vector.normalize(),
-                enemy_sight,
+                to_player_vector.normalize_or_zero(),
+                vision_cone.far_range,
                 true,
                 QueryFilter::new().exclude_sensors(),
             ) {
                 entity == player_entity
             } else {
                 false
-            } && player_invisibility.is_none());
+            };
+
+        let suspicion_change = if in_line_of_sight {
+            visibility * SUSPICION_GAIN_PER_SECOND
+        } else {
+            -SUSPICION_DECAY_PER_SECOND
+        };
+
+        **suspicion = (**suspicion + suspicion_change * time.delta_seconds()).clamp(0., 1.);
 
-        if see_player {
-            if matches!(*enemy_state, EnemyState::Idle) {
+        if in_line_of_sight && (alerted || **suspicion >= 1.) {
+            if !alerted {
                 audio.play(audio_assets.notice.clone());
+                enemy_alerted.send(EnemyAlerted {
+                    enemy: enemy_entity,
+                });
             }
 
+            **suspicion = 1.;
+
             *enemy_state = EnemyState::Alert {
                 target: player_transform.translation.truncate(),
             };
-        } else {
-            *enemy_state = EnemyState::Idle;
+        } else if let EnemyState::Alert { target } = *enemy_state {
+            // Lost the player, so the enemy goes looking around the place it last saw them
+            *enemy_state = EnemyState::Search {
+                last_known: target,
+                timer: Timer::from_seconds(search_settings.duration, TimerMode::Once),
+            };
         }
     }
 }
@@ -0,0 +1,162 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::{QueryFilter, RapierContext};
+
+use crate::{cleanup::cleanup, player::Player, unit::Direction, GameState, WorldState};
+
+use super::{
+    sight::{see_player, sees_all_around, Suspicion, VisionCone},
+    EnemyState,
+};
+
+/// How many rays make up the edge of a cone, a full circle gets twice as many
+const SIGHT_CONE_RAYS: usize = 32;
+
+/// Drawn below the enemies and the player, but above the floor
+const SIGHT_CONE_Z: f32 = 3.5;
+
+pub struct SightConePlugin;
+
+impl Plugin for SightConePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (spawn_sight_cones, update_sight_cones.after(see_player))
+                .in_set(OnUpdate(GameState::Playing)),
+        )
+        .add_system(cleanup::<SightCone>.in_schedule(OnExit(WorldState::Yes)));
+    }
+}
+
+/// The drawn sight area of an enemy. It's a separate entity, so it doesn't get scaled
+/// and flipped together with the enemy's sprite.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SightCone {
+    pub owner: Entity,
+}
+
+fn spawn_sight_cones(
+    mut commands: Commands,
+    enemy_query: Query<Entity, Added<VisionCone>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for enemy_entity in enemy_query.iter() {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(Mesh::new(PrimitiveTopology::TriangleList))
+                    .into(),
+                material: materials.add(ColorMaterial::from(Color::NONE)),
+                ..default()
+            },
+            SightCone {
+                owner: enemy_entity,
+            },
+            Name::new("Sight Cone"),
+        ));
+    }
+}
+
+fn sight_cone_color(enemy_state: &EnemyState, suspicion: f32) -> Color {
+    match enemy_state {
+        EnemyState::Alert { .. } => Color::rgba(1.0, 0.1, 0.1, 0.25),
+        EnemyState::Stun { .. } => Color::NONE,
+        EnemyState::Search { .. } => Color::rgba(1.0, 0.55, 0.0, 0.25),
+        EnemyState::Idle if suspicion > 0. => Color::rgba(1.0, 0.55, 0.0, 0.25),
+        EnemyState::Idle => Color::rgba(1.0, 1.0, 0.8, 0.15),
+    }
+}
+
+/// Casts the same rays `see_player` does and builds the fan of triangles from where they hit.
+fn update_sight_cones(
+    mut commands: Commands,
+    mut cone_query: Query<(
+        Entity,
+        &SightCone,
+        &Mesh2dHandle,
+        &Handle<ColorMaterial>,
+        &mut Transform,
+    )>,
+    enemy_query: Query<
+        (&EnemyState, &Suspicion, &VisionCone, &Transform, &Direction),
+        Without<SightCone>,
+    >,
+    player_query: Query<Entity, With<Player>>,
+    rapier_context: Res<RapierContext>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let player_entity = player_query.single();
+
+    for (cone_entity, sight_cone, mesh_handle, material_handle, mut cone_transform) in
+        cone_query.iter_mut()
+    {
+        let (enemy_state, suspicion, vision_cone, enemy_transform, enemy_direction) =
+            if let Ok(enemy) = enemy_query.get(sight_cone.owner) {
+                enemy
+            } else {
+                // The enemy is gone, so is the cone
+                commands.entity(cone_entity).despawn_recursive();
+                continue;
+            };
+
+        let origin = enemy_transform.translation.truncate();
+        cone_transform.translation = origin.extend(SIGHT_CONE_Z);
+
+        if let Some(material) = materials.get_mut(material_handle) {
+            material.color = sight_cone_color(enemy_state, **suspicion);
+        }
+
+        let (half_angle, ray_count) = if sees_all_around(enemy_state) {
+            (PI, SIGHT_CONE_RAYS * 2)
+        } else {
+            (vision_cone.half_angle.to_radians(), SIGHT_CONE_RAYS)
+        };
+
+        let facing = enemy_direction.as_vec2();
+        let facing_angle = facing.y.atan2(facing.x);
+
+        let mut positions = vec![[0., 0., 0.]];
+
+        for ray in 0..=ray_count {
+            let angle = facing_angle - half_angle + 2. * half_angle * ray as f32 / ray_count as f32;
+            let ray_direction = Vec2::from_angle(angle);
+
+            // The player doesn't block the sight, only walls and closed doors do
+            let distance = if let Some((_, toi)) = rapier_context.cast_ray(
+                origin,
+                ray_direction,
+                vision_cone.far_range,
+                true,
+                QueryFilter::new()
+                    .exclude_sensors()
+                    .exclude_collider(player_entity),
+            ) {
+                toi
+            } else {
+                vision_cone.far_range
+            };
+
+            positions.push((ray_direction * distance).extend(0.).to_array());
+        }
+
+        let indices = (1..positions.len() as u32 - 1)
+            .flat_map(|index| [0, index, index + 1])
+            .collect::<Vec<_>>();
+
+        if let Some(mesh) = meshes.get_mut(&mesh_handle.0) {
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_NORMAL,
+                vec![[0f32, 0., 1.]; positions.len()],
+            );
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0f32, 0.]; positions.len()]);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            mesh.set_indices(Some(Indices::U32(indices)));
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::LevelEvent;
+use bevy_rapier2d::prelude::{
+    ActiveEvents, Ccd, Collider, CollisionEvent, CollisionGroups, GravityScale, Group, RigidBody,
+    Sensor, Velocity,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    cleanup::cleanup,
+    player::{
+        effect::{apply_player_damage, Invincibility, Vulnerability},
+        Player,
+    },
+    unit::Health,
+    GameState, WorldState,
+};
+
+pub struct EnemyWeaponPlugin;
+
+impl Plugin for EnemyWeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Weapon>()
+            .register_type::<WeaponStats>()
+            .register_type::<Projectile>()
+            .add_systems(
+                (
+                    projectile_hits,
+                    expire_projectiles,
+                    despawn_projectiles_on_level_change,
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(cleanup::<Projectile>.in_schedule(OnExit(WorldState::Yes)));
+    }
+}
+
+/// What the enemy attacks the player with, set by the `Weapon` field in LDtk
+#[derive(Component, Reflect, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum Weapon {
+    /// Shoots the player point-blank
+    #[default]
+    Melee,
+    Pistol,
+    Shotgun,
+}
+
+/// How a weapon behaves, after the enemy's archetype had its say
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component)]
+pub struct WeaponStats {
+    /// Seconds between two shots
+    pub fire_interval: f32,
+    /// How far the player can be for the enemy to start shooting
+    pub range: f32,
+    /// Damage of a single projectile, or of the hit itself for melee
+    pub damage: f32,
+    /// Angle the projectiles get scattered over, in degrees
+    pub spread: f32,
+    pub projectiles_per_shot: u32,
+    pub projectile_speed: f32,
+}
+
+impl Default for WeaponStats {
+    fn default() -> Self {
+        Weapon::default().stats()
+    }
+}
+
+impl WeaponStats {
+    pub fn is_ranged(&self) -> bool {
+        self.projectiles_per_shot > 0
+    }
+}
+
+impl Weapon {
+    pub fn stats(&self) -> WeaponStats {
+        match self {
+            Weapon::Melee => WeaponStats {
+                fire_interval: 1.,
+                range: 25.,
+                damage: 20.,
+                spread: 0.,
+                projectiles_per_shot: 0,
+                projectile_speed: 0.,
+            },
+            Weapon::Pistol => WeaponStats {
+                fire_interval: 1.2,
+                range: 400.,
+                damage: 15.,
+                spread: 6.,
+                projectiles_per_shot: 1,
+                projectile_speed: 700.,
+            },
+            Weapon::Shotgun => WeaponStats {
+                fire_interval: 2.,
+                range: 250.,
+                damage: 8.,
+                spread: 25.,
+                projectiles_per_shot: 5,
+                projectile_speed: 600.,
+            },
+        }
+    }
+
+    pub fn from_ldtk_value(value: &str) -> Option<Self> {
+        match value {
+            "Melee" => Some(Weapon::Melee),
+            "Pistol" => Some(Weapon::Pistol),
+            "Shotgun" => Some(Weapon::Shotgun),
+            _ => None,
+        }
+    }
+}
+
+/// Collision group of the projectiles, they fly through each other
+const PROJECTILE_GROUP: Group = Group::GROUP_2;
+
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component)]
+pub struct Projectile {
+    pub damage: f32,
+    /// The projectile disappears when it doesn't hit anything in time
+    pub lifetime: Timer,
+}
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    projectile: Projectile,
+    #[bundle]
+    sprite_bundle: SpriteBundle,
+    name: Name,
+    rigidbody: RigidBody,
+    collider: Collider,
+    collision_groups: CollisionGroups,
+    velocity: Velocity,
+    gravity_scale: GravityScale,
+    ccd: Ccd,
+    active_events: ActiveEvents,
+}
+
+impl ProjectileBundle {
+    pub fn new(position: Vec2, direction: Vec2, stats: &WeaponStats) -> Self {
+        Self {
+            projectile: Projectile {
+                damage: stats.damage,
+                lifetime: Timer::from_seconds(
+                    stats.range * 2. / stats.projectile_speed,
+                    TimerMode::Once,
+                ),
+            },
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(1.0, 0.85, 0.3),
+                    custom_size: Some(Vec2::splat(6.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(5.5)),
+                ..default()
+            },
+            name: Name::new("Projectile"),
+            rigidbody: RigidBody::Dynamic,
+            collider: Collider::ball(3.),
+            collision_groups: CollisionGroups::new(
+                PROJECTILE_GROUP,
+                Group::ALL.difference(PROJECTILE_GROUP),
+            ),
+            velocity: Velocity::linear(direction * stats.projectile_speed),
+            gravity_scale: GravityScale(0.),
+            // They are fast enough to go through a wall in a single frame otherwise.
+            // That's also why they aren't sensors, CCD only works for solid colliders.
+            ccd: Ccd::enabled(),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+        }
+    }
+}
+
+/// Directions of the projectiles of a single shot, scattered randomly around the aim.
+pub fn scatter_shot(aim: Vec2, stats: &WeaponStats, rng: &mut impl Rng) -> Vec<Vec2> {
+    (0..stats.projectiles_per_shot)
+        .map(|_| {
+            let angle = (rng.gen::<f32>() - 0.5) * stats.spread.to_radians();
+
+            Vec2::from_angle(angle).rotate(aim.normalize_or_zero())
+        })
+        .collect()
+}
+
+fn projectile_hits(
+    mut commands: Commands,
+    projectile_query: Query<&Projectile>,
+    mut player_query: Query<
+        (&mut Health, Option<&Invincibility>, Option<&Vulnerability>),
+        With<Player>,
+    >,
+    sensor_query: Query<(), With<Sensor>>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    for event in collision_events.iter() {
+        let (first, second) = if let CollisionEvent::Started(first, second, _) = event {
+            (*first, *second)
+        } else {
+            continue;
+        };
+
+        let (projectile_entity, projectile, other) =
+            if let Ok(projectile) = projectile_query.get(first) {
+                (first, projectile, second)
+            } else if let Ok(projectile) = projectile_query.get(second) {
+                (second, projectile, first)
+            } else {
+                continue;
+            };
+
+        // Flying through pills, exits and open doors
+        if sensor_query.contains(other) {
+            continue;
+        }
+
+        if let Ok((mut health, invincibility, vulnerability)) = player_query.get_mut(other) {
+            if *apply_player_damage(&mut health, projectile.damage, invincibility, vulnerability) {
+                state.set(GameState::GameOver);
+            }
+        }
+
+        // Anything solid stops the projectile, be it the player or a wall
+        commands.entity(projectile_entity).despawn_recursive();
+    }
+}
+
+fn expire_projectiles(
+    mut commands: Commands,
+    mut projectile_query: Query<(Entity, &mut Projectile)>,
+    time: Res<Time>,
+) {
+    for (entity, mut projectile) in projectile_query.iter_mut() {
+        if projectile.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Projectiles still flying when the level changes would keep flying through the new one.
+fn despawn_projectiles_on_level_change(
+    mut commands: Commands,
+    projectile_query: Query<Entity, With<Projectile>>,
+    mut level_events: EventReader<LevelEvent>,
+) {
+    if !level_events
+        .iter()
+        .any(|event| matches!(event, LevelEvent::SpawnTriggered(_)))
+    {
+        return;
+    }
+
+    for entity in projectile_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
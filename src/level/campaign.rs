@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::LdtkJson, LevelSelection};
+
+/// Ordered list of levels played one after another during a single run
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Campaign {
+    levels: Vec<LevelSelection>,
+    current: usize,
+}
+
+impl Campaign {
+    pub fn new(levels: Vec<LevelSelection>) -> Self {
+        Self { levels, current: 0 }
+    }
+
+    /// Builds a campaign going through the levels in the order they are defined in the project.
+    pub fn from_project(project: &LdtkJson) -> Self {
+        Self::new(
+            project
+                .levels
+                .iter()
+                .map(|level| LevelSelection::Iid(level.iid.clone()))
+                .collect(),
+        )
+    }
+
+    pub fn current_level(&self) -> Option<&LevelSelection> {
+        self.levels.get(self.current)
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.current + 1 < self.levels.len()
+    }
+
+    /// Moves on to the next level.
+    ///
+    /// # Returns
+    /// The selection of the next level or `None` if the campaign is finished.
+    pub fn advance(&mut self) -> Option<&LevelSelection> {
+        if !self.has_next() {
+            return None;
+        }
+
+        self.current += 1;
+
+        self.current_level()
+    }
+}
@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{
+    prelude::{FieldValue, LayerInstance, LdtkEntity, TilesetDefinition},
+    EntityInstance,
+};
+use bevy_rapier2d::prelude::{Collider, Sensor};
+
+use crate::{actions::BurstActions, loading::TextureAssets, player::Player};
+
+use super::{navmesh::block_nav_mesh_areas, WorldNavMesh};
+
+/// Size of a door, one level tile
+pub const DOOR_SIZE: f32 = 64.;
+
+/// How close the player has to be to open or close a door
+const DOOR_INTERACTION_DISTANCE: f32 = 80.;
+
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Door {
+    pub sideways: bool,
+    pub open: bool,
+}
+
+impl Door {
+    pub fn area(position: Vec2) -> Rect {
+        Rect::from_center_size(position, Vec2::splat(DOOR_SIZE))
+    }
+}
+
+/// Sent when a part of the nav mesh stops being walkable, e.g. when a door closes
+#[derive(Clone, Debug)]
+pub struct NavMeshAreaBlocked {
+    pub area: Rect,
+}
+
+#[derive(Bundle)]
+pub struct DoorBundle {
+    door: Door,
+    #[bundle]
+    sprite_bundle: SpriteBundle,
+    name: Name,
+    collider: Collider,
+}
+
+impl Default for DoorBundle {
+    fn default() -> Self {
+        Self {
+            door: Door::default(),
+            sprite_bundle: SpriteBundle::default(),
+            name: Name::new("Door"),
+            collider: Collider::cuboid(DOOR_SIZE / 2., DOOR_SIZE / 2.),
+        }
+    }
+}
+
+impl LdtkEntity for DoorBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        // Problems are reported by the level validation, here we just take the fallback
+        let (door, _problems) = parse_door_fields(entity_instance);
+
+        Self { door, ..default() }
+    }
+}
+
+/// Reads the door configuration from its LDtk fields.
+pub fn parse_door_fields(entity_instance: &EntityInstance) -> (Door, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut door = Door::default();
+
+    for field in &entity_instance.field_instances {
+        match field.identifier.as_str() {
+            "Sideways" => match &field.value {
+                FieldValue::Bool(value) => door.sideways = *value,
+                other => problems.push(format!("Sideways field is not a bool: {:?}", other)),
+            },
+            "Open" => match &field.value {
+                FieldValue::Bool(value) => door.open = *value,
+                other => problems.push(format!("Open field is not a bool: {:?}", other)),
+            },
+            other => problems.push(format!("Unknown door field: {}", other)),
+        }
+    }
+
+    (door, problems)
+}
+
+pub fn update_door_texture(
+    mut query: Query<(&Door, &mut Handle<Image>, &mut Sprite), Added<Door>>,
+    textures: Res<TextureAssets>,
+) {
+    for (door, mut texture, mut sprite) in query.iter_mut() {
+        *texture = if door.sideways {
+            textures.door_sideways.clone()
+        } else {
+            textures.door.clone()
+        };
+
+        sprite.custom_size = Some(Vec2::splat(DOOR_SIZE));
+    }
+}
+
+pub fn toggle_doors(
+    mut door_query: Query<(&mut Door, &GlobalTransform)>,
+    player_query: Query<&Transform, With<Player>>,
+    mut burst_actions: EventReader<BurstActions>,
+) {
+    if !burst_actions
+        .iter()
+        .any(|action| *action == BurstActions::Interact)
+    {
+        return;
+    }
+
+    let player_position = player_query.single().translation.truncate();
+
+    let closest_door = door_query
+        .iter_mut()
+        .map(|(door, transform)| {
+            let position = transform.translation().truncate();
+
+            (door, position, position.distance(player_position))
+        })
+        .filter(|(_, _, distance)| *distance <= DOOR_INTERACTION_DISTANCE)
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+    if let Some((mut door, position, _)) = closest_door {
+        // Closing the door on yourself would get you stuck in it
+        if door.open && Door::area(position).contains(player_position) {
+            return;
+        }
+
+        door.open = !door.open;
+    }
+}
+
+pub fn update_door_state(
+    mut commands: Commands,
+    mut door_query: Query<(Entity, &Door, &mut Sprite), Changed<Door>>,
+) {
+    for (entity, door, mut sprite) in door_query.iter_mut() {
+        if door.open {
+            // Open doors only leave their frame behind, which nothing bumps into
+            commands.entity(entity).insert(Sensor);
+            sprite.color.set_a(0.3);
+        } else {
+            commands.entity(entity).remove::<Sensor>();
+            sprite.color.set_a(1.0);
+        }
+    }
+}
+
+/// Cuts the closed doors out of the nav meshes whenever a door changes
+/// or a new nav mesh gets baked.
+///
+/// Every door state gets its nav mesh built only once, after that it's swapped in from
+/// the ones kept in [`WorldNavMesh::door_states`].
+pub fn update_door_obstacles(
+    changed_door_query: Query<(&Door, &GlobalTransform), Changed<Door>>,
+    door_query: Query<(&Door, &GlobalTransform)>,
+    added_nav_mesh_query: Query<(), Added<WorldNavMesh>>,
+    mut nav_mesh_query: Query<&mut WorldNavMesh>,
+    mut area_blocked: EventWriter<NavMeshAreaBlocked>,
+) {
+    if changed_door_query.is_empty() && added_nav_mesh_query.is_empty() {
+        return;
+    }
+
+    let mut closed_doors = door_query
+        .iter()
+        .filter(|(door, _)| !door.open)
+        .map(|(_, transform)| transform.translation().truncate().round().as_ivec2())
+        .collect::<Vec<_>>();
+    closed_doors.sort_by_key(|position| (position.x, position.y));
+
+    for mut world_nav_mesh in nav_mesh_query.iter_mut() {
+        if world_nav_mesh.closed_doors == closed_doors {
+            continue;
+        }
+
+        let world_nav_mesh = &mut *world_nav_mesh;
+
+        let nav_mesh = if let Some(nav_mesh) = world_nav_mesh.door_states.remove(&closed_doors) {
+            nav_mesh
+        } else {
+            let blocked = closed_doors
+                .iter()
+                .map(|position| Door::area(position.as_vec2()))
+                .collect::<Vec<_>>();
+
+            if let Some(nav_mesh) = block_nav_mesh_areas(&world_nav_mesh.baked, &blocked) {
+                nav_mesh
+            } else {
+                warn!(
+                    "Couldn't block the closed doors in the {:?} nav mesh",
+                    world_nav_mesh.agent_class
+                );
+                continue;
+            }
+        };
+
+        let previous = std::mem::replace(&mut world_nav_mesh.nav_mesh, nav_mesh);
+        let previous_doors =
+            std::mem::replace(&mut world_nav_mesh.closed_doors, closed_doors.clone());
+        world_nav_mesh.door_states.insert(previous_doors, previous);
+    }
+
+    for (door, transform) in changed_door_query.iter() {
+        if !door.open {
+            area_blocked.send(NavMeshAreaBlocked {
+                area: Door::area(transform.translation().truncate()),
+            });
+        }
+    }
+}
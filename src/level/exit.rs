@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{
+    prelude::{FieldValue, LayerInstance, LdtkEntity, TilesetDefinition},
+    EntityInstance,
+};
+use bevy_rapier2d::prelude::{ActiveCollisionTypes, Collider, RapierContext, RigidBody, Sensor};
+
+use crate::{loading::TextureAssets, player::Player, GameState};
+
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Exit {
+    pub sideways: bool,
+}
+
+#[derive(Bundle)]
+pub struct ExitBundle {
+    exit: Exit,
+    #[bundle]
+    sprite_bundle: SpriteBundle,
+    name: Name,
+    rigidbody: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+    active_collision_types: ActiveCollisionTypes,
+}
+
+impl Default for ExitBundle {
+    fn default() -> Self {
+        Self {
+            exit: Exit::default(),
+            sprite_bundle: SpriteBundle::default(),
+            name: Name::new("Exit"),
+            rigidbody: RigidBody::KinematicPositionBased,
+            collider: Collider::cuboid(32., 32.),
+            sensor: Sensor,
+            active_collision_types: ActiveCollisionTypes::all(),
+        }
+    }
+}
+
+impl LdtkEntity for ExitBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        // Problems are reported by the level validation, here we just take the fallback
+        let (exit, _problems) = parse_exit_fields(entity_instance);
+
+        Self { exit, ..default() }
+    }
+}
+
+/// Reads the exit configuration from its LDtk fields.
+pub fn parse_exit_fields(entity_instance: &EntityInstance) -> (Exit, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut exit = Exit::default();
+
+    for field in &entity_instance.field_instances {
+        match field.identifier.as_str() {
+            "Sideways" => match &field.value {
+                FieldValue::Bool(value) => exit.sideways = *value,
+                other => problems.push(format!("Sideways field is not a bool: {:?}", other)),
+            },
+            other => problems.push(format!("Unknown exit field: {}", other)),
+        }
+    }
+
+    (exit, problems)
+}
+
+pub fn update_exit_texture(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Exit, &mut Handle<Image>, &mut Sprite), Added<Exit>>,
+    textures: Res<TextureAssets>,
+) {
+    for (entity, exit, mut texture, mut sprite) in query.iter_mut() {
+        *texture = if exit.sideways {
+            textures.exit_door_sideways.clone()
+        } else {
+            textures.exit_door.clone()
+        };
+
+        sprite.custom_size = Some(Vec2::splat(64.));
+
+        // The sign hangs right above the door, over everything walking past it
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn(SpriteBundle {
+                texture: textures.exit_sign.clone(),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(48.)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 56., 10.),
+                ..default()
+            });
+        });
+    }
+}
+
+pub fn reach_exit(
+    exit_query: Query<Entity, With<Exit>>,
+    player_query: Query<Entity, With<Player>>,
+    rapier_context: Res<RapierContext>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    let player_entity = player_query.single();
+
+    for exit_entity in exit_query.iter() {
+        if rapier_context.intersection_pair(exit_entity, player_entity) == Some(true) {
+            state.set(GameState::LevelComplete);
+        }
+    }
+}
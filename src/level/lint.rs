@@ -0,0 +1,222 @@
+use ::navmesh::{NavMesh, NavPathMode, NavQuery, NavVec3};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{
+    ldtk::{LdtkJson, Level},
+    prelude::LayerInstance,
+    utils::ldtk_pixel_coords_to_translation_pivoted,
+    EntityInstance,
+};
+
+use crate::{
+    enemy::{archetype::EnemyArchetypes, parse_enemy_fields},
+    player::{PLAYER_COLLIDER_HEIGHT, PLAYER_COLLIDER_WIDTH},
+};
+
+use super::{
+    nav_agent::NavMeshAgents,
+    navmesh::{bake_grid_nav_mesh, NavGrid},
+    navmesh_cache::{grid_content_hash, CachedNavMesh},
+    validation::{validate_project, LevelProblem},
+    NAV_MESH_CELL_VALUE,
+};
+
+/// Checks everything the game checks when loading the project, plus whether
+/// the levels can actually be played through.
+pub fn lint_project(project: &LdtkJson, archetypes: Option<&EnemyArchetypes>) -> Vec<LevelProblem> {
+    let mut problems = validate_project(project, archetypes);
+
+    for level in &project.levels {
+        problems.extend(lint_level(level));
+    }
+
+    problems
+}
+
+fn lint_level(level: &Level) -> Vec<LevelProblem> {
+    let mut problems = Vec::new();
+
+    let level_problem = |message: String| LevelProblem {
+        level: level.identifier.clone(),
+        entity: "Level".to_string(),
+        entity_iid: level.iid.clone(),
+        message,
+    };
+
+    let entity_problem = |entity_instance: &EntityInstance, message: String| LevelProblem {
+        level: level.identifier.clone(),
+        entity: entity_instance.identifier.clone(),
+        entity_iid: entity_instance.iid.clone(),
+        message,
+    };
+
+    let layer_instances = level.layer_instances.as_deref().unwrap_or_default();
+
+    let mut grid = if let Some(layer_instance) = layer_instances
+        .iter()
+        .find(|layer_instance| layer_instance.int_grid_csv.contains(&NAV_MESH_CELL_VALUE))
+    {
+        NavGrid::from_int_grid(layer_instance, NAV_MESH_CELL_VALUE)
+    } else {
+        problems.push(level_problem("Level has no nav mesh cells".to_string()));
+        return problems;
+    };
+
+    // Doors are baked into their own triangles, the same way the game does it
+    for layer_instance in layer_instances {
+        for entity_instance in &layer_instance.entity_instances {
+            if entity_instance.identifier != "Door" {
+                continue;
+            }
+
+            if let Some(tile) = grid.tile_at(entity_translation(entity_instance, layer_instance)) {
+                grid.obstacles.insert(tile);
+            } else {
+                problems.push(entity_problem(
+                    entity_instance,
+                    "Door is outside of the level".to_string(),
+                ));
+            }
+        }
+    }
+
+    // A cache left behind by a debug run has to match what the game would bake itself
+    for (agent_class, agent_size) in NavMeshAgents::default().agents {
+        if let Some(cached) = CachedNavMesh::read(&level.iid, agent_class) {
+            let content_hash = grid_content_hash(&grid, agent_size);
+
+            if cached.content_hash != content_hash {
+                problems.push(level_problem(format!(
+                    "{:?} nav mesh cache is out of date, run the game to rebake it",
+                    agent_class
+                )));
+            } else if cached
+                != CachedNavMesh::new(
+                    content_hash,
+                    &bake_grid_nav_mesh(&grid, agent_size),
+                    grid.origin,
+                )
+            {
+                problems.push(level_problem(format!(
+                    "Cached {:?} nav mesh differs from a fresh bake",
+                    agent_class
+                )));
+            }
+        }
+    }
+
+    // Pills and the exit have to be reached by the player, so their reachability is
+    // checked on a nav mesh baked for the player's collider instead of an enemy's
+    let nav_mesh = bake_grid_nav_mesh(
+        &grid,
+        Vec2::new(PLAYER_COLLIDER_WIDTH, PLAYER_COLLIDER_HEIGHT),
+    );
+
+    let entities = layer_instances
+        .iter()
+        .flat_map(|layer_instance| {
+            layer_instance
+                .entity_instances
+                .iter()
+                .map(move |entity_instance| (entity_instance, layer_instance))
+        })
+        .collect::<Vec<_>>();
+
+    let spawns = entities
+        .iter()
+        .filter(|(entity_instance, _)| entity_instance.identifier == "PlayerSpawn")
+        .collect::<Vec<_>>();
+
+    if spawns.len() != 1 {
+        problems.push(level_problem(format!(
+            "Expected exactly one PlayerSpawn, found {}",
+            spawns.len()
+        )));
+    }
+
+    let spawn = spawns.first().map(|(entity_instance, layer_instance)| {
+        (
+            entity_instance,
+            entity_translation(entity_instance, layer_instance),
+        )
+    });
+
+    if let Some((spawn_instance, spawn_position)) = spawn {
+        if !grid.is_walkable_at(spawn_position) {
+            problems.push(entity_problem(
+                spawn_instance,
+                "PlayerSpawn is not on a nav mesh cell".to_string(),
+            ));
+        }
+    }
+
+    for (entity_instance, layer_instance) in &entities {
+        let position = entity_translation(entity_instance, layer_instance);
+
+        match entity_instance.identifier.as_str() {
+            "Enemy" => {
+                let (enemy_fields, _problems) = parse_enemy_fields(entity_instance, layer_instance);
+
+                for (index, waypoint) in enemy_fields
+                    .movement_type
+                    .waypoints()
+                    .into_iter()
+                    .enumerate()
+                {
+                    if !grid.is_walkable_at(waypoint) {
+                        problems.push(entity_problem(
+                            entity_instance,
+                            format!(
+                                "Waypoint #{} at ({}, {}) is not on a nav mesh cell",
+                                index, waypoint.x, waypoint.y
+                            ),
+                        ));
+                    }
+                }
+            }
+            "Pill" | "Exit" => {
+                if let Some((_, spawn_position)) = spawn {
+                    if !is_reachable(&nav_mesh, &grid, spawn_position, position) {
+                        problems.push(entity_problem(
+                            entity_instance,
+                            format!(
+                                "{} is not reachable from the PlayerSpawn",
+                                entity_instance.identifier
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    problems
+}
+
+fn entity_translation(entity_instance: &EntityInstance, layer_instance: &LayerInstance) -> Vec2 {
+    ldtk_pixel_coords_to_translation_pivoted(
+        entity_instance.px,
+        layer_instance.c_hei * layer_instance.grid_size,
+        IVec2::new(entity_instance.width, entity_instance.height),
+        entity_instance.pivot,
+    )
+}
+
+fn is_reachable(nav_mesh: &NavMesh, grid: &NavGrid, from: Vec2, to: Vec2) -> bool {
+    // Anything further than a tile away from the nav mesh is standing in a wall
+    let max_snap_distance = grid.grid_size.max_element();
+
+    let snap = |point: Vec2| {
+        nav_mesh
+            .closest_point(NavVec3::new(point.x, point.y, 0.), NavQuery::Accuracy)
+            .filter(|closest| Vec2::new(closest.x, closest.y).distance(point) <= max_snap_distance)
+    };
+
+    if let (Some(from), Some(to)) = (snap(from), snap(to)) {
+        nav_mesh
+            .find_path(from, to, NavQuery::Accuracy, NavPathMode::Accuracy)
+            .is_some()
+    } else {
+        false
+    }
+}
@@ -1,41 +1,83 @@
 use crate::cleanup::cleanup;
-use crate::enemy::{ENEMY_COLLIDER_HEIGHT, ENEMY_COLLIDER_WIDTH};
 use crate::pill::PillBundle;
 use crate::{enemy::EnemyBundle, loading::LevelAssets};
 use crate::{GameState, WorldState};
 use ::navmesh::NavMesh;
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_tilemap::prelude::{TilemapGridSize, TilemapSize};
 use bevy_ecs_tilemap::tiles::TilePos;
 use bevy_rapier2d::prelude::{Collider, PhysicsSet};
 
-use self::navmesh::{draw_nav_mesh, NavMeshBuilder};
+use self::campaign::Campaign;
+use self::door::{
+    toggle_doors, update_door_obstacles, update_door_state, update_door_texture, Door, DoorBundle,
+    NavMeshAreaBlocked,
+};
+use self::exit::{reach_exit, update_exit_texture, Exit, ExitBundle};
+use self::nav_agent::{AgentClass, NavMeshAgents};
+use self::navmesh::{draw_nav_mesh, NavGrid};
+use self::navmesh_cache::load_or_bake_nav_mesh;
+use self::validation::{report_level_problems, LevelValidationReport};
 
-mod navmesh;
+pub mod campaign;
+pub(crate) mod door;
+mod exit;
+pub(crate) mod lint;
+pub(crate) mod nav_agent;
+pub(crate) mod navmesh;
+mod navmesh_cache;
+pub(crate) mod validation;
+
+/// IntGrid value of the wall tiles
+pub const WALL_VALUE: i32 = 1;
+/// IntGrid value of the tiles enemies can walk on
+pub const NAV_MESH_CELL_VALUE: i32 = 2;
 
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(LdtkPlugin)
+        app.register_type::<Exit>()
+            .register_type::<Door>()
+            .register_type::<AgentClass>()
+            .add_plugin(LdtkPlugin)
             .configure_set(LdtkSystemSet::ProcessApi.before(PhysicsSet::SyncBackend))
             .insert_resource(LevelSelection::Uid(0))
+            .init_resource::<Campaign>()
+            .init_resource::<NavMeshAgents>()
+            .init_resource::<LevelHotReload>()
+            .add_event::<NavMeshAreaBlocked>()
+            .init_resource::<LevelValidationReport>()
             .insert_resource(LdtkSettings {
                 level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
-                    load_level_neighbors: true,
+                    load_level_neighbors: false,
                 },
                 level_background: LevelBackground::Nonexistent,
                 ..Default::default()
             })
-            .register_ldtk_int_cell::<WallBundle>(1)
-            .register_ldtk_int_cell::<NavMeshCellBundle>(2)
+            .register_ldtk_int_cell::<WallBundle>(WALL_VALUE)
+            .register_ldtk_int_cell::<NavMeshCellBundle>(NAV_MESH_CELL_VALUE)
             .register_ldtk_entity::<EnemyBundle>("Enemy")
             .register_ldtk_entity::<PillBundle>("Pill")
             .register_ldtk_entity::<PlayerSpawnBundle>("PlayerSpawn")
-            .add_system(ldtk_setup.in_schedule(OnEnter(GameState::Playing)))
-            .add_system(generate_nav_mesh.in_set(OnUpdate(GameState::Playing)))
+            .register_ldtk_entity::<ExitBundle>("Exit")
+            .register_ldtk_entity::<DoorBundle>("Door")
+            .add_system(ldtk_setup.in_schedule(OnEnter(WorldState::Yes)))
+            .add_system(report_level_problems)
+            .add_systems(
+                (
+                    generate_nav_mesh,
+                    update_exit_texture,
+                    reach_exit,
+                    update_door_texture,
+                    toggle_doors,
+                    update_door_state.after(toggle_doors),
+                    update_door_obstacles.after(toggle_doors),
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
             .add_systems(
                 (cleanup::<Handle<LdtkAsset>>, cleanup::<WorldNavMesh>)
                     .in_schedule(OnExit(WorldState::Yes)),
@@ -43,18 +85,38 @@ impl Plugin for LevelPlugin {
 
         #[cfg(debug_assertions)]
         {
-            app.add_system(draw_nav_mesh.in_set(OnUpdate(GameState::Playing)));
+            app.add_system(draw_nav_mesh.in_set(OnUpdate(GameState::Playing)))
+                .add_systems(
+                    (
+                        detect_level_hot_reload.before(finish_level_hot_reload),
+                        finish_level_hot_reload,
+                    )
+                        .in_set(OnUpdate(WorldState::Yes)),
+                );
         }
     }
 }
 
+/// Set while a level modified on disk is being respawned, so the player stays where they were
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct LevelHotReload {
+    /// Iids of the levels that were spawned when the project changed and still have to be respawned
+    pub levels: HashSet<String>,
+}
+
+impl LevelHotReload {
+    pub fn in_progress(&self) -> bool {
+        !self.levels.is_empty()
+    }
+}
+
 #[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
 #[reflect(Component)]
 pub struct Wall;
 
 pub fn grid_collider(grid_cell: IntGridCell) -> Collider {
     match grid_cell.value {
-        1 => Collider::cuboid(32.0, 32.0),
+        WALL_VALUE => Collider::cuboid(32.0, 32.0),
         _ => Collider::cuboid(1.0, 1.0),
     }
 }
@@ -88,92 +150,187 @@ pub struct PlayerSpawnBundle {
     global_transform: GlobalTransform,
 }
 
+/// Nav mesh of a level, baked for one agent class
 #[derive(Component, Default, Deref, DerefMut, Clone, Debug)]
-pub struct WorldNavMesh(pub NavMesh);
+pub struct WorldNavMesh {
+    pub level_iid: String,
+    pub agent_class: AgentClass,
+    /// The nav mesh units walk on, without the areas currently blocked by closed doors
+    #[deref]
+    pub nav_mesh: NavMesh,
+    /// Positions of the closed doors `nav_mesh` was built for
+    pub closed_doors: Vec<IVec2>,
+    /// Nav meshes for the other door states seen so far, so toggling a door back
+    /// just swaps in the mesh from before instead of building it again
+    pub door_states: HashMap<Vec<IVec2>, NavMesh>,
+    /// The nav mesh as it was baked, with everything walkable
+    pub baked: NavMesh,
+}
 
 fn generate_nav_mesh(
     mut commands: Commands,
-    nav_mesh_cell_query: Query<(&TilePos, &GlobalTransform), With<NavMeshCell>>,
-    level_query: Query<(&TilemapGridSize, &TilemapSize)>,
+    nav_mesh_cell_query: Query<(&Parent, &TilePos, &GlobalTransform), With<NavMeshCell>>,
+    door_query: Query<(&Parent, &GlobalTransform), With<Door>>,
+    nav_mesh_query: Query<(Entity, &WorldNavMesh)>,
+    layer_query: Query<(Entity, &Parent, &TilemapGridSize, &TilemapSize)>,
+    ldtk_level_query: Query<(Entity, &Handle<LdtkLevel>)>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    agents: Res<NavMeshAgents>,
     mut level_events: EventReader<LevelEvent>,
 ) {
     for event in level_events.iter() {
         match event {
-            LevelEvent::Transformed(_iid) => {
-                let (grid_size, tilemap_size) = level_query.single();
+            LevelEvent::Transformed(iid) => {
+                // While a level gets swapped both the old and the new one exist,
+                // so everything is looked up under the level that was transformed
+                let level_entity = ldtk_level_query.iter().find_map(|(entity, handle)| {
+                    ldtk_levels
+                        .get(handle)
+                        .filter(|ldtk_level| ldtk_level.level.iid == *iid)
+                        .map(|_| entity)
+                });
+
+                let level_entity = if let Some(level_entity) = level_entity {
+                    level_entity
+                } else {
+                    warn!("Level {iid} was transformed but isn't spawned");
+                    continue;
+                };
+
+                let layers: Vec<_> = layer_query
+                    .iter()
+                    .filter(|(_, parent, _, _)| parent.get() == level_entity)
+                    .collect();
 
-                let mut nav_mesh_builder = NavMeshBuilder::new();
+                let (grid_size, tilemap_size) = if let Some((_, _, grid_size, tilemap_size)) =
+                    layers.iter().find(|(layer, _, _, _)| {
+                        nav_mesh_cell_query
+                            .iter()
+                            .any(|(parent, _, _)| parent.get() == *layer)
+                    }) {
+                    (*grid_size, *tilemap_size)
+                } else {
+                    warn!("Level {iid} has no walkable cells to bake a nav mesh from");
+                    continue;
+                };
 
-                let width = tilemap_size.x;
-                let height = tilemap_size.y;
-                let mut map = HashMap::new();
+                let is_in_level =
+                    |parent: &Parent| layers.iter().any(|(layer, _, _, _)| *layer == parent.get());
 
-                for (nav_mesh_cell, cell_transform) in nav_mesh_cell_query.iter() {
-                    map.insert(
-                        (nav_mesh_cell.x, nav_mesh_cell.y),
-                        cell_transform.translation().truncate(),
-                    );
+                let mut grid = NavGrid {
+                    width: tilemap_size.x,
+                    height: tilemap_size.y,
+                    grid_size: Vec2::new(grid_size.x, grid_size.y),
+                    ..default()
+                };
+
+                for (_, nav_mesh_cell, cell_transform) in nav_mesh_cell_query
+                    .iter()
+                    .filter(|(parent, _, _)| is_in_level(parent))
+                {
+                    grid.cells.insert((nav_mesh_cell.x, nav_mesh_cell.y));
+                    grid.origin = cell_transform.translation().truncate()
+                        - Vec2::new(nav_mesh_cell.x as f32, nav_mesh_cell.y as f32)
+                            * grid.grid_size;
                 }
 
-                for x in 1..=width {
-                    for y in 1..=height {
-                        if !map.contains_key(&(x, y)) {
-                            continue;
-                        }
-
-                        let top = -(grid_size.y
-                            - if !map.contains_key(&(x, y - 1)) {
-                                ENEMY_COLLIDER_HEIGHT
-                            } else {
-                                0.0
-                            })
-                            / 2.;
-
-                        let bottom = (grid_size.y
-                            - if !map.contains_key(&(x, y + 1)) {
-                                ENEMY_COLLIDER_HEIGHT
-                            } else {
-                                0.0
-                            })
-                            / 2.;
-
-                        let left = -(grid_size.x
-                            - if !map.contains_key(&(x - 1, y)) {
-                                ENEMY_COLLIDER_WIDTH
-                            } else {
-                                0.0
-                            })
-                            / 2.;
-
-                        let right = (grid_size.x
-                            - if !map.contains_key(&(x + 1, y)) {
-                                ENEMY_COLLIDER_WIDTH
-                            } else {
-                                0.0
-                            })
-                            / 2.;
-
-                        let tile_pos = map[&(x, y)];
-
-                        nav_mesh_builder.insert_rect(
-                            tile_pos + Vec2::new(left, top),
-                            tile_pos + Vec2::new(right, top),
-                            tile_pos + Vec2::new(right, bottom),
-                            tile_pos + Vec2::new(left, bottom),
-                        );
+                for (_, door_transform) in
+                    door_query.iter().filter(|(parent, _)| is_in_level(parent))
+                {
+                    if let Some(tile) = grid.tile_at(door_transform.translation().truncate()) {
+                        grid.obstacles.insert(tile);
                     }
                 }
 
-                let nav_mesh = nav_mesh_builder.bake();
+                // A reloaded level gets transformed again, its old nav meshes have to go
+                for (nav_mesh_entity, world_nav_mesh) in nav_mesh_query.iter() {
+                    if world_nav_mesh.level_iid == *iid {
+                        commands.entity(nav_mesh_entity).despawn_recursive();
+                    }
+                }
+
+                for (agent_class, agent_size) in &agents.agents {
+                    let nav_mesh = load_or_bake_nav_mesh(iid, *agent_class, &grid, *agent_size);
+
+                    let nav_mesh_entity = commands
+                        .spawn((
+                            TransformBundle::default(),
+                            WorldNavMesh {
+                                level_iid: iid.clone(),
+                                agent_class: *agent_class,
+                                baked: nav_mesh.clone(),
+                                nav_mesh,
+                                closed_doors: Vec::new(),
+                                door_states: HashMap::new(),
+                            },
+                        ))
+                        .id();
 
-                commands.spawn((TransformBundle::default(), WorldNavMesh(nav_mesh)));
+                    // The nav meshes live under the level so they get despawned together with it
+                    commands.entity(level_entity).add_child(nav_mesh_entity);
+                }
             }
             _ => {}
         }
     }
 }
 
-fn ldtk_setup(mut commands: Commands, level_assets: Res<LevelAssets>) {
+#[cfg(debug_assertions)]
+fn detect_level_hot_reload(
+    mut asset_events: EventReader<AssetEvent<LdtkAsset>>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    mut hot_reload: ResMut<LevelHotReload>,
+) {
+    for event in asset_events.iter() {
+        if let AssetEvent::Modified { .. } = event {
+            info!("Level changed on disk, reloading");
+
+            // Only the levels spawned right now get respawned from the changed project
+            hot_reload.levels.extend(
+                level_query
+                    .iter()
+                    .filter_map(|handle| ldtk_levels.get(handle))
+                    .map(|ldtk_level| ldtk_level.level.iid.clone()),
+            );
+        }
+    }
+}
+
+/// Ends the hot reload once the respawned levels got transformed, which is a frame
+/// after their entities were spawned, so the player spawn was seen by then
+#[cfg(debug_assertions)]
+fn finish_level_hot_reload(
+    mut level_events: EventReader<LevelEvent>,
+    mut hot_reload: ResMut<LevelHotReload>,
+) {
+    for event in level_events.iter() {
+        if let LevelEvent::Transformed(iid) = event {
+            if hot_reload.levels.contains(iid) {
+                hot_reload.levels.remove(iid);
+            }
+        }
+    }
+}
+
+fn ldtk_setup(
+    mut commands: Commands,
+    level_assets: Res<LevelAssets>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    let campaign = ldtk_assets
+        .get(&level_assets.ldtk_handle)
+        .map(|ldtk_asset| Campaign::from_project(&ldtk_asset.project))
+        .unwrap_or_default();
+
+    if let Some(first_level) = campaign.current_level() {
+        *level_selection = first_level.clone();
+    }
+
+    commands.insert_resource(campaign);
+    commands.insert_resource(LevelHotReload::default());
+
     commands.spawn(LdtkWorldBundle {
         ldtk_handle: level_assets.ldtk_handle.clone(),
         ..Default::default()
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LdtkLevel;
+use bevy_rapier2d::prelude::Collider;
+
+use crate::enemy::{ENEMY_COLLIDER_HEIGHT, ENEMY_COLLIDER_WIDTH};
+
+use super::WorldNavMesh;
+
+/// Size class of the units walking on a nav mesh. Every class gets its own nav mesh.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub enum AgentClass {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+/// Iid of the level a unit was spawned in. While levels get swapped the old and the new one
+/// both exist, so units only walk on the nav meshes of their own level.
+#[derive(Component, Default, Debug, Clone, PartialEq, Eq, Deref)]
+pub struct AgentLevel(pub String);
+
+/// The agent classes nav meshes get baked for, together with the biggest
+/// collider size (full width and height) that fits each of them.
+#[derive(Resource, Clone, Debug)]
+pub struct NavMeshAgents {
+    pub agents: Vec<(AgentClass, Vec2)>,
+}
+
+impl Default for NavMeshAgents {
+    fn default() -> Self {
+        Self {
+            agents: vec![
+                (
+                    AgentClass::Small,
+                    Vec2::new(ENEMY_COLLIDER_WIDTH / 2., ENEMY_COLLIDER_HEIGHT / 2.),
+                ),
+                (
+                    AgentClass::Medium,
+                    Vec2::new(ENEMY_COLLIDER_WIDTH, ENEMY_COLLIDER_HEIGHT),
+                ),
+                (
+                    AgentClass::Large,
+                    Vec2::new(ENEMY_COLLIDER_WIDTH * 1.5, ENEMY_COLLIDER_HEIGHT),
+                ),
+            ],
+        }
+    }
+}
+
+impl NavMeshAgents {
+    pub fn size_of(&self, class: AgentClass) -> Option<Vec2> {
+        self.agents
+            .iter()
+            .find(|(agent_class, _)| *agent_class == class)
+            .map(|(_, size)| *size)
+    }
+
+    /// Picks the smallest class whose nav mesh keeps a unit of the given size out of the walls.
+    /// Units bigger than every class get the biggest one.
+    pub fn class_for_size(&self, size: Vec2) -> AgentClass {
+        let mut agents = self.agents.clone();
+        agents.sort_by(|(_, a), (_, b)| (a.x * a.y).total_cmp(&(b.x * b.y)));
+
+        agents
+            .iter()
+            .find(|(_, agent_size)| size.x <= agent_size.x && size.y <= agent_size.y)
+            .or(agents.last())
+            .map(|(class, _)| *class)
+            .unwrap_or_default()
+    }
+}
+
+/// Finds the nav mesh of the level baked for the given agent class.
+pub fn nav_mesh_for_agent<'a>(
+    nav_mesh_query: &'a Query<&WorldNavMesh>,
+    level: &AgentLevel,
+    class: AgentClass,
+) -> Option<&'a WorldNavMesh> {
+    nav_mesh_query
+        .iter()
+        .find(|nav_mesh| nav_mesh.level_iid == level.0 && nav_mesh.agent_class == class)
+}
+
+/// Gives every unit with an agent class the level it was spawned in,
+/// by looking for the level entity among its ancestors.
+pub fn assign_agent_level(
+    mut commands: Commands,
+    agent_query: Query<Entity, (With<AgentClass>, Without<AgentLevel>)>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&Handle<LdtkLevel>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+) {
+    for agent_entity in agent_query.iter() {
+        let mut ancestor = agent_entity;
+
+        while let Ok(parent) = parent_query.get(ancestor) {
+            ancestor = parent.get();
+
+            let level = level_query
+                .get(ancestor)
+                .ok()
+                .and_then(|handle| ldtk_levels.get(handle));
+
+            if let Some(ldtk_level) = level {
+                commands
+                    .entity(agent_entity)
+                    .insert(AgentLevel(ldtk_level.level.iid.clone()));
+                break;
+            }
+        }
+    }
+}
+
+/// Gives every unit with a collider and an agent class the class matching its collider size.
+pub fn assign_agent_class(
+    mut agent_query: Query<
+        (&mut AgentClass, &Collider, &Transform),
+        Or<(Added<AgentClass>, Changed<Collider>)>,
+    >,
+    agents: Res<NavMeshAgents>,
+) {
+    for (mut agent_class, collider, transform) in agent_query.iter_mut() {
+        let half_extents = if let Some(cuboid) = collider.as_cuboid() {
+            cuboid.half_extents()
+        } else {
+            continue;
+        };
+
+        let size = half_extents * 2. * transform.scale.truncate();
+
+        agent_class.set_if_neq(agents.class_for_size(size));
+    }
+}
@@ -1,91 +1,409 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::prelude::LayerInstance;
 use bevy_prototype_debug_lines::DebugLines;
-use navmesh::{NavMesh, NavVec3, NavTriangle};
+use navmesh::{NavMesh, NavTriangle, NavVec3};
 
-use super::WorldNavMesh;
+use super::{nav_agent::AgentClass, WorldNavMesh};
+
+/// Walkable tiles of a level, indexed the same way as the tilemap's `TilePos`.
+///
+/// Every tile inside `width` and `height` can be walkable, including the ones on the
+/// border of the level. Outside the grid counts as a wall when baking.
+#[derive(Clone, Debug, Default)]
+pub struct NavGrid {
+    pub cells: HashSet<(u32, u32)>,
+    /// Walkable tiles that can get blocked at runtime, like doors. These always end up
+    /// as their own triangles, so they can be cut out of the nav mesh without rebaking it.
+    pub obstacles: HashSet<(u32, u32)>,
+    pub width: u32,
+    pub height: u32,
+    pub grid_size: Vec2,
+    /// Translation of the center of the tile at (0, 0)
+    pub origin: Vec2,
+}
+
+impl NavGrid {
+    /// Reads the tiles with the given value from an IntGrid layer, in level space.
+    pub fn from_int_grid(layer_instance: &LayerInstance, value: i32) -> Self {
+        let width = layer_instance.c_wid as u32;
+        let height = layer_instance.c_hei as u32;
+        let grid_size = Vec2::splat(layer_instance.grid_size as f32);
+
+        let cells = layer_instance
+            .int_grid_csv
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == value)
+            // LDtk counts rows from the top, the tilemap from the bottom
+            .map(|(index, _)| (index as u32 % width, height - 1 - index as u32 / width))
+            .collect();
+
+        Self {
+            cells,
+            obstacles: HashSet::new(),
+            width,
+            height,
+            grid_size,
+            origin: grid_size / 2.,
+        }
+    }
+
+    /// The tile the point lies on, if it's inside the grid.
+    pub fn tile_at(&self, point: Vec2) -> Option<(u32, u32)> {
+        let tile = ((point - self.origin) / self.grid_size).round();
+
+        if tile.x < 0. || tile.y < 0. || tile.x >= self.width as f32 || tile.y >= self.height as f32
+        {
+            return None;
+        }
+
+        Some((tile.x as u32, tile.y as u32))
+    }
+
+    pub fn tile_center(&self, x: u32, y: u32) -> Vec2 {
+        self.origin + Vec2::new(x as f32, y as f32) * self.grid_size
+    }
+
+    /// Checks whether the point lies on a walkable tile. Points on the border between tiles
+    /// count as walkable if any of the tiles is.
+    pub fn is_walkable_at(&self, point: Vec2) -> bool {
+        let tile = (point - self.origin) / self.grid_size + Vec2::splat(0.5);
+
+        let xs = [(tile.x - 0.01).floor(), (tile.x + 0.01).floor()];
+        let ys = [(tile.y - 0.01).floor(), (tile.y + 0.01).floor()];
+
+        xs.iter().any(|&x| {
+            ys.iter()
+                .any(|&y| x >= 0. && y >= 0. && self.cells.contains(&(x as u32, y as u32)))
+        })
+    }
+}
+
+/// Bakes a nav mesh from the walkable tiles, keeping an agent of the given size
+/// away from the walls.
+///
+/// Neighbouring tiles are merged into rectangles first, so open rooms end up as a handful
+/// of triangles instead of two for every tile.
+pub fn bake_grid_nav_mesh(grid: &NavGrid, agent_size: Vec2) -> NavMesh {
+    let rects = merge_grid_rects(grid, agent_size);
+
+    // Corners of the neighbouring rectangles that lie on an edge have to become vertices
+    // of that edge too, otherwise the triangles wouldn't share edges and wouldn't get connected
+    let mut horizontal_points = HashMap::<u32, Vec<f32>>::new();
+    let mut vertical_points = HashMap::<u32, Vec<f32>>::new();
+
+    for rect in &rects {
+        for corner in [
+            rect.min,
+            rect.max,
+            Vec2::new(rect.min.x, rect.max.y),
+            Vec2::new(rect.max.x, rect.min.y),
+        ] {
+            horizontal_points
+                .entry(float_key(corner.y))
+                .or_default()
+                .push(corner.x);
+            vertical_points
+                .entry(float_key(corner.x))
+                .or_default()
+                .push(corner.y);
+        }
+    }
+
+    for points in horizontal_points
+        .values_mut()
+        .chain(vertical_points.values_mut())
+    {
+        points.sort_by(f32::total_cmp);
+        points.dedup();
+    }
+
+    let mut nav_mesh_builder = NavMeshBuilder::new();
+
+    for rect in &rects {
+        let corners = [
+            rect.min,
+            Vec2::new(rect.max.x, rect.min.y),
+            rect.max,
+            Vec2::new(rect.min.x, rect.max.y),
+        ];
+
+        let mut polygon = Vec::with_capacity(4);
+
+        for (index, &start) in corners.iter().enumerate() {
+            let end = corners[(index + 1) % corners.len()];
+
+            polygon.push(start);
+
+            if start.y == end.y {
+                let points = &horizontal_points[&float_key(start.y)];
+                polygon.extend(
+                    points_between(points, start.x, end.x)
+                        .into_iter()
+                        .map(|x| Vec2::new(x, start.y)),
+                );
+            } else {
+                let points = &vertical_points[&float_key(start.x)];
+                polygon.extend(
+                    points_between(points, start.y, end.y)
+                        .into_iter()
+                        .map(|y| Vec2::new(start.x, y)),
+                );
+            }
+        }
+
+        nav_mesh_builder.insert_polygon(&polygon);
+    }
+
+    nav_mesh_builder.bake()
+}
+
+/// Which sides of the tile are against a wall
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TileInsets {
+    top: bool,
+    bottom: bool,
+    left: bool,
+    right: bool,
+}
+
+/// Greedily merges the walkable tiles into rectangles. Tiles only get merged if the rectangle
+/// would be shrunk away from the walls the same way as each of the tiles on its own.
+fn merge_grid_rects(grid: &NavGrid, agent_size: Vec2) -> Vec<Rect> {
+    let walkable = |x: u32, y: u32| grid.cells.contains(&(x, y));
+
+    let insets = |x: u32, y: u32| TileInsets {
+        top: y == 0 || !walkable(x, y - 1),
+        bottom: !walkable(x, y + 1),
+        left: x == 0 || !walkable(x - 1, y),
+        right: !walkable(x + 1, y),
+    };
+
+    let mut merged = HashSet::new();
+    let mut rects = Vec::new();
+
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if !walkable(x, y) || merged.contains(&(x, y)) {
+                continue;
+            }
+
+            let start = insets(x, y);
+            let free = |x: u32, y: u32| {
+                walkable(x, y) && !merged.contains(&(x, y)) && !grid.obstacles.contains(&(x, y))
+            };
+            let is_obstacle = grid.obstacles.contains(&(x, y));
+
+            let mut max_x = x;
+
+            while !is_obstacle && free(max_x + 1, y) && {
+                let next = insets(max_x + 1, y);
+                next.top == start.top && next.bottom == start.bottom
+            } {
+                max_x += 1;
+            }
+
+            let end = insets(max_x, y);
+            let mut max_y = y;
+
+            while !is_obstacle && (x..=max_x).all(|row_x| free(row_x, max_y + 1)) && {
+                let first = insets(x, max_y + 1);
+                let last = insets(max_x, max_y + 1);
+
+                first.left == start.left
+                    && last.right == end.right
+                    && (x..=max_x).all(|row_x| insets(row_x, max_y + 1).bottom == first.bottom)
+            } {
+                max_y += 1;
+            }
+
+            for merged_x in x..=max_x {
+                for merged_y in y..=max_y {
+                    merged.insert((merged_x, merged_y));
+                }
+            }
+
+            let bottom = insets(x, max_y);
+
+            let inset = |against_wall: bool, tile_size: f32, agent_size: f32| {
+                (tile_size - if against_wall { agent_size } else { 0.0 }) / 2.
+            };
+
+            let min = grid.tile_center(x, y)
+                - Vec2::new(
+                    inset(start.left, grid.grid_size.x, agent_size.x),
+                    inset(start.top, grid.grid_size.y, agent_size.y),
+                );
+            let max = grid.tile_center(max_x, max_y)
+                + Vec2::new(
+                    inset(end.right, grid.grid_size.x, agent_size.x),
+                    inset(bottom.bottom, grid.grid_size.y, agent_size.y),
+                );
+
+            // Not using `Rect::from_corners`, narrow corridors can end up inverted
+            // like single tiles did
+            rects.push(Rect { min, max });
+        }
+    }
+
+    rects
+}
+
+fn float_key(value: f32) -> u32 {
+    // Adding zero turns -0.0 into 0.0 so both end up under the same key
+    (value + 0.0).to_bits()
+}
+
+/// Values strictly between `from` and `to`, ordered from `from` to `to`.
+fn points_between(points: &[f32], from: f32, to: f32) -> Vec<f32> {
+    let (low, high) = if from < to { (from, to) } else { (to, from) };
+
+    let start = points.partition_point(|&point| point <= low);
+    let end = points.partition_point(|&point| point < high).max(start);
+
+    let mut between = points[start..end].to_vec();
+
+    if from > to {
+        between.reverse();
+    }
+
+    between
+}
+
+/// Builds a copy of the nav mesh without the triangles lying in any of the blocked areas.
+///
+/// The vertices and triangles stay the same as in the baked nav mesh, only the connections
+/// between them get recalculated. This still isn't cheap, so callers keep the result around
+/// instead of calling it again for the same areas.
+pub fn block_nav_mesh_areas(baked: &NavMesh, blocked: &[Rect]) -> Option<NavMesh> {
+    let vertices = baked.vertices();
+
+    let triangles = baked
+        .triangles()
+        .iter()
+        .filter(|triangle| {
+            let center = [triangle.first, triangle.second, triangle.third]
+                .iter()
+                .map(|index| Vec2::new(vertices[*index as usize].x, vertices[*index as usize].y))
+                .sum::<Vec2>()
+                / 3.;
+
+            !blocked.iter().any(|area| area.contains(center))
+        })
+        .cloned()
+        .collect();
+
+    NavMesh::new(vertices.to_vec(), triangles).ok()
+}
 
 pub struct NavMeshBuilder {
-	vertices: Vec<NavVec3>,
-	triangles: Vec<NavTriangle>,
+    vertices: Vec<NavVec3>,
+    vertex_indices: HashMap<(u32, u32), usize>,
+    triangles: Vec<NavTriangle>,
 }
 
 impl NavMeshBuilder {
-	pub fn new() -> Self {
-		Self {
-			vertices: Vec::new(),
-			triangles: Vec::new(),
-		}
-	}
-
-	pub fn bake(&self) -> NavMesh {
-		// It might cause performance issues
-		NavMesh::new(self.vertices.clone(), self.triangles.clone()).expect("Invalid input for baking the NavMesh")
-	}
-
-	fn vertex_index(&mut self, vertex: NavVec3) -> usize {
-		match self.vertices.iter().position(|&r| r == vertex) {
-			Some(index) => index,
-			None => {
-				self.vertices.push(vertex);
-				self.vertices.len() - 1
-			}
-		}
-	}
-
-	#[allow(unused)]
-	pub fn insert_triangle(&mut self, p1: Vec2, p2: Vec2, p3: Vec2) {
-		let indices = (
-			self.vertex_index(NavVec3::new(p1.x, p1.y, 0.0)) as u32,
-			self.vertex_index(NavVec3::new(p2.x, p2.y, 0.0)) as u32,
-			self.vertex_index(NavVec3::new(p3.x, p3.y, 0.0)) as u32,
-		);
-
-		self.triangles.push(indices.into());
-	}
-
-	pub fn insert_rect(&mut self, p1: Vec2, p2: Vec2, p3: Vec2, p4: Vec2) {
-		let indices = (
-			self.vertex_index(NavVec3::new(p1.x, p1.y, 0.0)) as u32,
-			self.vertex_index(NavVec3::new(p2.x, p2.y, 0.0)) as u32,
-			self.vertex_index(NavVec3::new(p3.x, p3.y, 0.0)) as u32,
-			self.vertex_index(NavVec3::new(p4.x, p4.y, 0.0)) as u32,
-		);
-
-		self.triangles.push((indices.0, indices.1, indices.2).into());
-		self.triangles.push((indices.0, indices.3, indices.2).into());
-	}
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            vertex_indices: HashMap::new(),
+            triangles: Vec::new(),
+        }
+    }
+
+    pub fn bake(&self) -> NavMesh {
+        NavMesh::new(self.vertices.clone(), self.triangles.clone())
+            .expect("Invalid input for baking the NavMesh")
+    }
+
+    fn vertex_index(&mut self, vertex: NavVec3) -> usize {
+        let vertices = &mut self.vertices;
+
+        *self
+            .vertex_indices
+            .entry((float_key(vertex.x), float_key(vertex.y)))
+            .or_insert_with(|| {
+                vertices.push(vertex);
+                vertices.len() - 1
+            })
+    }
+
+    pub fn insert_triangle(&mut self, p1: Vec2, p2: Vec2, p3: Vec2) {
+        let indices = (
+            self.vertex_index(NavVec3::new(p1.x, p1.y, 0.0)) as u32,
+            self.vertex_index(NavVec3::new(p2.x, p2.y, 0.0)) as u32,
+            self.vertex_index(NavVec3::new(p3.x, p3.y, 0.0)) as u32,
+        );
+
+        self.triangles.push(indices.into());
+    }
+
+    pub fn insert_rect(&mut self, p1: Vec2, p2: Vec2, p3: Vec2, p4: Vec2) {
+        let indices = (
+            self.vertex_index(NavVec3::new(p1.x, p1.y, 0.0)) as u32,
+            self.vertex_index(NavVec3::new(p2.x, p2.y, 0.0)) as u32,
+            self.vertex_index(NavVec3::new(p3.x, p3.y, 0.0)) as u32,
+            self.vertex_index(NavVec3::new(p4.x, p4.y, 0.0)) as u32,
+        );
+
+        self.triangles
+            .push((indices.0, indices.1, indices.2).into());
+        self.triangles
+            .push((indices.0, indices.3, indices.2).into());
+    }
+
+    /// Inserts a convex polygon with its points in order.
+    pub fn insert_polygon(&mut self, points: &[Vec2]) {
+        if let &[p1, p2, p3, p4] = points {
+            self.insert_rect(p1, p2, p3, p4);
+            return;
+        }
+
+        // Fanning out from the center, the extra points on the edges are collinear with the corners
+        let center = points.iter().copied().sum::<Vec2>() / points.len() as f32;
+
+        for (index, &point) in points.iter().enumerate() {
+            let next = points[(index + 1) % points.len()];
+
+            self.insert_triangle(center, point, next);
+        }
+    }
 }
 
 pub fn draw_nav_mesh(nav_mesh_query: Query<&WorldNavMesh>, mut lines: ResMut<DebugLines>) {
-	let nav_mesh = if let Ok(nav_mesh) = nav_mesh_query.get_single() {
-		nav_mesh
-	} else {
-		return;
-	};
-
-	let vertices = nav_mesh.vertices();
-
-	for triangle in nav_mesh.triangles() {
-		let first = Vec3::new(vertices[triangle.first as usize].x, vertices[triangle.first as usize].y, 0.0);
-		let second = Vec3::new(vertices[triangle.second as usize].x, vertices[triangle.second as usize].y, 0.0);
-		let third = Vec3::new(vertices[triangle.third as usize].x, vertices[triangle.third as usize].y, 0.0);
-
-		lines.line(
-			first,
-			second,
-			0.0
-		);
-
-		lines.line(
-			second,
-			third,
-			0.0
-		);
-
-		lines.line(
-			first,
-			third,
-			0.0
-		);
-	}
-}
\ No newline at end of file
+    // Drawing all of them on top of each other would be unreadable, so only the default agent's.
+    // Levels don't overlap, so every level's mesh gets drawn.
+    for nav_mesh in nav_mesh_query
+        .iter()
+        .filter(|nav_mesh| nav_mesh.agent_class == AgentClass::default())
+    {
+        let vertices = nav_mesh.vertices();
+
+        for triangle in nav_mesh.triangles() {
+            let first = Vec3::new(
+                vertices[triangle.first as usize].x,
+                vertices[triangle.first as usize].y,
+                0.0,
+            );
+            let second = Vec3::new(
+                vertices[triangle.second as usize].x,
+                vertices[triangle.second as usize].y,
+                0.0,
+            );
+            let third = Vec3::new(
+                vertices[triangle.third as usize].x,
+                vertices[triangle.third as usize].y,
+                0.0,
+            );
+
+            lines.line(first, second, 0.0);
+
+            lines.line(second, third, 0.0);
+
+            lines.line(first, third, 0.0);
+        }
+    }
+}
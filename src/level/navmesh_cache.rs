@@ -0,0 +1,180 @@
+use std::{fs, hash::Hasher, path::PathBuf};
+
+use ::navmesh::{NavMesh, NavVec3};
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+use bevy::asset::FileAssetIo;
+use bevy::prelude::*;
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    nav_agent::AgentClass,
+    navmesh::{bake_grid_nav_mesh, NavGrid},
+};
+
+/// Needs to be bumped whenever the baking changes, so old caches don't get picked up
+const CACHE_VERSION: u32 = 3;
+
+/// Baked nav meshes are stored next to the LDtk projects, one file per level and agent class.
+/// Relative to the asset folder, so it doesn't depend on where the game was started from.
+pub const CACHE_DIRECTORY: &str = "ldtk/navmesh-cache";
+
+/// A baked nav mesh together with the hash of the level content it was baked from.
+///
+/// Positions are stored relative to the grid's origin, so moving the level around
+/// in the world doesn't invalidate the cache.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CachedNavMesh {
+    pub content_hash: u64,
+    vertices: Vec<[f32; 2]>,
+    triangles: Vec<[u32; 3]>,
+}
+
+impl CachedNavMesh {
+    pub fn new(content_hash: u64, nav_mesh: &NavMesh, origin: Vec2) -> Self {
+        Self {
+            content_hash,
+            vertices: nav_mesh
+                .vertices()
+                .iter()
+                .map(|vertex| [vertex.x - origin.x, vertex.y - origin.y])
+                .collect(),
+            triangles: nav_mesh
+                .triangles()
+                .iter()
+                .map(|triangle| [triangle.first, triangle.second, triangle.third])
+                .collect(),
+        }
+    }
+
+    pub fn to_nav_mesh(&self, origin: Vec2) -> Result<NavMesh, String> {
+        NavMesh::new(
+            self.vertices
+                .iter()
+                .map(|[x, y]| NavVec3::new(x + origin.x, y + origin.y, 0.))
+                .collect(),
+            self.triangles
+                .iter()
+                .map(|[first, second, third]| (*first, *second, *third).into())
+                .collect(),
+        )
+        .map_err(|error| format!("{:?}", error))
+    }
+
+    pub fn read(level_iid: &str, agent_class: AgentClass) -> Option<Self> {
+        let contents = fs::read_to_string(cache_path(level_iid, agent_class)).ok()?;
+
+        ron::from_str(&contents).ok()
+    }
+
+    pub fn write(&self, level_iid: &str, agent_class: AgentClass) -> Result<(), String> {
+        let contents = ron::to_string(self).map_err(|error| error.to_string())?;
+
+        fs::create_dir_all(cache_directory()).map_err(|error| error.to_string())?;
+        fs::write(cache_path(level_iid, agent_class), contents).map_err(|error| error.to_string())
+    }
+}
+
+pub fn cache_path(level_iid: &str, agent_class: AgentClass) -> PathBuf {
+    cache_directory().join(format!("{}-{:?}.ron", level_iid, agent_class))
+}
+
+/// Resolves the cache directory the same way Bevy finds the asset folder.
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+fn cache_directory() -> PathBuf {
+    FileAssetIo::get_base_path()
+        .join("assets")
+        .join(CACHE_DIRECTORY)
+}
+
+/// There's no asset folder on disk to resolve against, reads and writes just fail.
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+fn cache_directory() -> PathBuf {
+    PathBuf::from("assets").join(CACHE_DIRECTORY)
+}
+
+/// Hashes everything the baked nav mesh depends on.
+pub fn grid_content_hash(grid: &NavGrid, agent_size: Vec2) -> u64 {
+    let mut cells = grid.cells.iter().copied().collect::<Vec<_>>();
+    cells.sort_unstable();
+
+    let mut obstacles = grid.obstacles.iter().copied().collect::<Vec<_>>();
+    obstacles.sort_unstable();
+
+    // FNV is fully specified and every value goes in as little endian bytes, so unlike
+    // with `DefaultHasher` the hash stays the same across Rust versions and platforms
+    let mut hasher = FnvHasher::default();
+    let mut write_u32 = |value: u32| hasher.write(&value.to_le_bytes());
+
+    write_u32(CACHE_VERSION);
+
+    for list in [&cells, &obstacles] {
+        write_u32(list.len() as u32);
+
+        for (x, y) in list {
+            write_u32(*x);
+            write_u32(*y);
+        }
+    }
+
+    write_u32(grid.width);
+    write_u32(grid.height);
+
+    for size in [grid.grid_size, agent_size] {
+        write_u32(size.x.to_bits());
+        write_u32(size.y.to_bits());
+    }
+
+    hasher.finish()
+}
+
+/// Loads the level's nav mesh for an agent class from the cache,
+/// baking and caching it if the level changed since.
+pub fn load_or_bake_nav_mesh(
+    level_iid: &str,
+    agent_class: AgentClass,
+    grid: &NavGrid,
+    agent_size: Vec2,
+) -> NavMesh {
+    // There's no file system to cache to on the web
+    if cfg!(target_arch = "wasm32") {
+        return bake_grid_nav_mesh(grid, agent_size);
+    }
+
+    let content_hash = grid_content_hash(grid, agent_size);
+
+    if let Some(cached) = CachedNavMesh::read(level_iid, agent_class) {
+        if cached.content_hash == content_hash {
+            match cached.to_nav_mesh(grid.origin) {
+                Ok(nav_mesh) => return nav_mesh,
+                Err(error) => warn!(
+                    "{:?} nav mesh cache for level {} is broken, baking it again: {}",
+                    agent_class, level_iid, error
+                ),
+            }
+        } else {
+            info!(
+                "{:?} nav mesh cache for level {} is out of date",
+                agent_class, level_iid
+            );
+        }
+    }
+
+    let nav_mesh = bake_grid_nav_mesh(grid, agent_size);
+
+    // Only development builds write the cache, a shipped game must not touch its install folder
+    if !cfg!(debug_assertions) {
+        return nav_mesh;
+    }
+
+    if let Err(error) =
+        CachedNavMesh::new(content_hash, &nav_mesh, grid.origin).write(level_iid, agent_class)
+    {
+        warn!(
+            "Couldn't cache the {:?} nav mesh for level {}: {}",
+            agent_class, level_iid, error
+        );
+    }
+
+    nav_mesh
+}
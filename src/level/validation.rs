@@ -0,0 +1,166 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy_console::PrintConsoleLine;
+use bevy_ecs_ldtk::{ldtk::LdtkJson, LdtkAsset};
+
+use crate::{
+    enemy::{archetype::EnemyArchetypes, parse_enemy_fields},
+    pill::parse_pill_fields,
+};
+
+use super::{door::parse_door_fields, exit::parse_exit_fields};
+
+/// Something wrong with an entity placed in a level
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelProblem {
+    pub level: String,
+    pub entity: String,
+    pub entity_iid: String,
+    pub message: String,
+}
+
+impl fmt::Display for LevelProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} ({}): {}",
+            self.level, self.entity, self.entity_iid, self.message
+        )
+    }
+}
+
+/// Problems found in the most recently loaded LDtk project
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LevelValidationReport {
+    pub problems: Vec<LevelProblem>,
+}
+
+/// Runs every entity in the project through the same parsing the game uses when spawning it
+/// and collects everything that had to fall back to a default.
+///
+/// Enemy archetypes are only checked when the archetypes are passed in.
+pub fn validate_project(
+    project: &LdtkJson,
+    archetypes: Option<&EnemyArchetypes>,
+) -> Vec<LevelProblem> {
+    let mut problems = Vec::new();
+
+    for level in &project.levels {
+        let has_exit = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer_instance| &layer_instance.entity_instances)
+            .any(|entity_instance| entity_instance.identifier == "Exit");
+
+        if !has_exit {
+            problems.push(LevelProblem {
+                level: level.identifier.clone(),
+                entity: "Level".to_string(),
+                entity_iid: level.iid.clone(),
+                message: "Level has no Exit, so it can't be completed".to_string(),
+            });
+        }
+
+        for layer_instance in level.layer_instances.iter().flatten() {
+            for entity_instance in &layer_instance.entity_instances {
+                let entity_problems = match entity_instance.identifier.as_str() {
+                    "Enemy" => {
+                        let (fields, mut problems) =
+                            parse_enemy_fields(entity_instance, layer_instance);
+
+                        if let Some(archetypes) = archetypes {
+                            if !archetypes.contains_key(&fields.archetype) {
+                                problems.push(format!(
+                                    "Archetype {} is not in enemies.archetypes.ron",
+                                    fields.archetype
+                                ));
+                            }
+                        }
+
+                        problems
+                    }
+                    "Pill" => parse_pill_fields(entity_instance).1,
+                    "Exit" => parse_exit_fields(entity_instance).1,
+                    "Door" => parse_door_fields(entity_instance).1,
+                    "PlayerSpawn" => entity_instance
+                        .field_instances
+                        .iter()
+                        .map(|field| format!("Unknown player spawn field: {}", field.identifier))
+                        .collect(),
+                    other => vec![format!("Unknown entity: {}", other)],
+                };
+
+                problems.extend(entity_problems.into_iter().map(|message| LevelProblem {
+                    level: level.identifier.clone(),
+                    entity: entity_instance.identifier.clone(),
+                    entity_iid: entity_instance.iid.clone(),
+                    message,
+                }));
+            }
+        }
+    }
+
+    problems
+}
+
+pub fn report_level_problems(
+    mut ldtk_events: EventReader<AssetEvent<LdtkAsset>>,
+    mut archetype_events: EventReader<AssetEvent<EnemyArchetypes>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    mut report: ResMut<LevelValidationReport>,
+    mut console_line: EventWriter<PrintConsoleLine>,
+) {
+    let changed_projects = ldtk_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                Some(handle.clone())
+            }
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect::<Vec<_>>();
+
+    // Changing the archetypes can make the enemies of every project valid or invalid
+    let archetypes_changed = archetype_events
+        .iter()
+        .any(|event| !matches!(event, AssetEvent::Removed { .. }));
+
+    let handles = if archetypes_changed {
+        ldtk_assets
+            .ids()
+            .map(|id| ldtk_assets.get_handle(id))
+            .collect()
+    } else {
+        changed_projects
+    };
+
+    // There's only the one archetypes file
+    let archetypes = archetypes.iter().next().map(|(_, archetypes)| archetypes);
+
+    for handle in handles {
+        let ldtk_asset = if let Some(ldtk_asset) = ldtk_assets.get(&handle) {
+            ldtk_asset
+        } else {
+            continue;
+        };
+
+        report.problems = validate_project(&ldtk_asset.project, archetypes);
+
+        if report.problems.is_empty() {
+            info!("Level validation passed");
+            continue;
+        }
+
+        console_line.send(PrintConsoleLine::new(
+            format!("{} problem(s) found in the level", report.problems.len()).into(),
+        ));
+
+        for problem in &report.problems {
+            warn!("{}", problem);
+            console_line.send(PrintConsoleLine::new(problem.to_string().into()));
+        }
+    }
+}
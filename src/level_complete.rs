@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_console::PrintConsoleLine;
+
+use bevy_ecs_ldtk::LevelSelection;
+
+use crate::{
+    cleanup::cleanup, level::campaign::Campaign, loading::FontAssets, GameState, WorldState,
+};
+
+pub struct LevelCompletePlugin;
+
+impl Plugin for LevelCompletePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup_level_complete_ui.in_schedule(OnEnter(GameState::LevelComplete)))
+            .add_systems(
+                (click_main_menu_button, click_next_level_button)
+                    .in_set(OnUpdate(GameState::LevelComplete)),
+            )
+            .add_system(cleanup::<LevelCompleteUI>.in_schedule(OnExit(GameState::LevelComplete)));
+    }
+}
+
+#[derive(Component)]
+pub struct LevelCompleteUI;
+
+#[derive(Component)]
+pub struct MainMenuButton;
+
+#[derive(Component)]
+pub struct NextLevelButton;
+
+pub fn setup_level_complete_ui(
+    mut commands: Commands,
+    mut console_line: EventWriter<PrintConsoleLine>,
+    font_assets: Res<FontAssets>,
+    campaign: Res<Campaign>,
+) {
+    console_line.send(PrintConsoleLine::new(format!(
+        "level {}/{} complete",
+        campaign.current_index() + 1,
+        campaign.level_count()
+    )));
+
+    let title = if campaign.has_next() {
+        "Level Complete"
+    } else {
+        "You Passed The Test"
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(20.0)),
+                ..Default::default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..Default::default()
+        })
+        .insert(Name::new("LevelCompleteUI"))
+        .insert(LevelCompleteUI)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font: font_assets.space_grotesk.clone(),
+                        font_size: 64.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(Name::new("Level Complete Title"));
+
+            if campaign.has_next() {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(200.0), Val::Px(50.0)),
+                            margin: UiRect::top(Val::Px(40.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        background_color: Color::BLACK.into(),
+                        ..Default::default()
+                    })
+                    .insert(Name::new("Next Level Button"))
+                    .insert(NextLevelButton)
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Next Level",
+                            TextStyle {
+                                font: font_assets.space_grotesk.clone(),
+                                font_size: 40.0,
+                                color: Color::WHITE,
+                            },
+                        ));
+                    });
+            }
+
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(200.0), Val::Px(50.0)),
+                        margin: UiRect::top(Val::Px(20.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    background_color: Color::BLACK.into(),
+                    ..Default::default()
+                })
+                .insert(Name::new("Main Menu Button"))
+                .insert(MainMenuButton)
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Main Menu",
+                        TextStyle {
+                            font: font_assets.space_grotesk.clone(),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+}
+
+fn click_main_menu_button(
+    mut game_state: ResMut<NextState<GameState>>,
+    mut world_state: ResMut<NextState<WorldState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<MainMenuButton>),
+    >,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                game_state.set(GameState::Menu);
+                world_state.set(WorldState::No);
+            }
+            Interaction::Hovered => {
+                *color = Color::rgba(0.8, 0.8, 0.8, 0.8).into();
+            }
+            Interaction::None => {
+                *color = Color::BLACK.into();
+            }
+        }
+    }
+}
+
+fn click_next_level_button(
+    mut game_state: ResMut<NextState<GameState>>,
+    mut campaign: ResMut<Campaign>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<NextLevelButton>),
+    >,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                if let Some(next_level) = campaign.advance() {
+                    *level_selection = next_level.clone();
+                    game_state.set(GameState::Playing);
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::rgba(0.8, 0.8, 0.8, 0.8).into();
+            }
+            Interaction::None => {
+                *color = Color::BLACK.into();
+            }
+        }
+    }
+}
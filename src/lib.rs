@@ -4,10 +4,12 @@ mod cleanup;
 mod enemy;
 mod game_over;
 mod level;
+mod level_complete;
 mod loading;
 mod menu;
 mod pill;
 mod player;
+mod rng;
 mod unit;
 
 use actions::ActionsPlugin;
@@ -29,10 +31,21 @@ use camera::CameraPlugin;
 use enemy::EnemyPlugin;
 use game_over::GameOverPlugin;
 use level::LevelPlugin;
+use level_complete::LevelCompletePlugin;
 use loading::LoadingPlugin;
 use menu::MenuPlugin;
 use pill::PillPlugin;
 use player::PlayerPlugin;
+use rng::RngPlugin;
+
+// Used by the `lint-level` binary and the benchmarks
+pub use enemy::archetype::EnemyArchetypes;
+pub use enemy::avoidance::{
+    separation_velocities, SpatialHash, DEFAULT_SEPARATION_RADIUS, DEFAULT_SEPARATION_STRENGTH,
+};
+pub use level::lint::lint_project;
+pub use level::navmesh::{bake_grid_nav_mesh, NavGrid};
+pub use level::validation::LevelProblem;
 
 // This example game uses States to separate logic
 // See https://bevy-cheatbook.github.io/programming/states.html
@@ -48,6 +61,8 @@ enum GameState {
     Menu,
     // Here the menu is drawn after player died but
     GameOver,
+    // Here the menu is drawn after player reached the level exit
+    LevelComplete,
 }
 
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
@@ -66,6 +81,7 @@ impl Plugin for GamePlugin {
         app.add_state::<GameState>()
             .add_state::<WorldState>()
             .add_plugin(LoadingPlugin)
+            .add_plugin(RngPlugin)
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
             .add_plugin(SpritesheetAnimationPlugin)
             .add_plugin(AudioPlugin)
@@ -73,6 +89,7 @@ impl Plugin for GamePlugin {
             .add_plugin(ActionsPlugin)
             .add_plugin(PlayerPlugin)
             .add_plugin(GameOverPlugin)
+            .add_plugin(LevelCompletePlugin)
             .add_plugin(PillPlugin)
             .add_plugin(CameraPlugin)
             .add_plugin(LevelPlugin)
@@ -1,8 +1,16 @@
-use crate::GameState;
-use bevy::prelude::*;
+use std::marker::PhantomData;
+
+use crate::{enemy::archetype::EnemyArchetypes, pill::PillCatalog, GameState};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
 use bevy_asset_loader::prelude::*;
 use bevy_ecs_ldtk::LdtkAsset;
 use bevy_kira_audio::AudioSource;
+use serde::de::DeserializeOwned;
 
 pub struct LoadingPlugin;
 
@@ -11,13 +19,55 @@ pub struct LoadingPlugin;
 /// If interested, take a look at <https://bevy-cheatbook.github.io/features/assets.html>
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading).continue_to_state(GameState::Menu),
-        )
-        .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, LevelAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading);
+        app.add_asset::<EnemyArchetypes>()
+            .add_asset_loader(RonAssetLoader::<EnemyArchetypes>::new(&["archetypes.ron"]))
+            .add_asset::<PillCatalog>()
+            .add_asset_loader(RonAssetLoader::<PillCatalog>::new(&["catalog.ron"]))
+            // Leaving the loading state is up to `load_pill_textures`,
+            // the pill textures are only known once the catalog is loaded
+            .add_loading_state(LoadingState::new(GameState::Loading))
+            .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, LevelAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, EnemyAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, PillAssets>(GameState::Loading)
+            .add_system(load_pill_textures.in_set(OnUpdate(GameState::Loading)));
+    }
+}
+
+/// Loads game data written in RON, so it can be tweaked without recompiling the game.
+/// Every kind of data gets its own file extension, like `archetypes.ron`.
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+
+impl<T: TypeUuid + DeserializeOwned + Send + Sync + 'static> AssetLoader for RonAssetLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<T>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
     }
 }
 
@@ -66,6 +116,16 @@ pub struct TextureAssets {
     pub player_body: Handle<Image>,
     #[asset(path = "textures/wall.png")]
     pub wall: Handle<Image>,
+    #[asset(path = "textures/exit-door.png")]
+    pub exit_door: Handle<Image>,
+    #[asset(path = "textures/exit-door-sideways-right.png")]
+    pub exit_door_sideways: Handle<Image>,
+    #[asset(path = "textures/exit-sign.png")]
+    pub exit_sign: Handle<Image>,
+    #[asset(path = "textures/door.png")]
+    pub door: Handle<Image>,
+    #[asset(path = "textures/door-sideways-right.png")]
+    pub door_sideways: Handle<Image>,
     #[asset(texture_atlas(tile_size_x = 200., tile_size_y = 260., columns = 5, rows = 9))]
     #[asset(path = "textures/enemy/enemy-up.png")]
     pub enemy_up: Handle<TextureAtlas>,
@@ -78,16 +138,6 @@ pub struct TextureAssets {
     #[asset(texture_atlas(tile_size_x = 640., tile_size_y = 480., columns = 5, rows = 10))]
     #[asset(path = "textures/enemy/enemy-right.png")]
     pub enemy_right: Handle<TextureAtlas>,
-    #[asset(path = "textures/pills/health_pill.png")]
-    pub health_pill: Handle<Image>,
-    #[asset(path = "textures/pills/speed_pill.png")]
-    pub speed_pill: Handle<Image>,
-    #[asset(path = "textures/pills/toxic_fart_pill.png")]
-    pub toxic_fart_pill: Handle<Image>,
-    #[asset(path = "textures/pills/invisibility_pill.png")]
-    pub invisibility_pill: Handle<Image>,
-    #[asset(path = "textures/pills/invincibility_pill.png")]
-    pub invincibility_pill: Handle<Image>,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -95,3 +145,80 @@ pub struct LevelAssets {
     #[asset(path = "ldtk/level1.ldtk")]
     pub ldtk_handle: Handle<LdtkAsset>,
 }
+
+#[derive(AssetCollection, Resource)]
+pub struct EnemyAssets {
+    #[asset(path = "enemies/enemies.archetypes.ron")]
+    pub archetypes: Handle<EnemyArchetypes>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct PillAssets {
+    #[asset(path = "pills/pills.catalog.ron")]
+    pub catalog: Handle<PillCatalog>,
+}
+
+/// Textures of every pill in the catalog by path, loaded before the game starts
+/// so the pills don't pop in once the level is there
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PillTextures(HashMap<String, Handle<Image>>);
+
+impl PillTextures {
+    /// The preloaded texture, or a freshly loaded one for pills added to the catalog since.
+    pub fn get(&self, path: &str, asset_server: &AssetServer) -> Handle<Image> {
+        self.0
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| asset_server.load(path))
+    }
+}
+
+/// Loads the textures of the pill catalog once the asset collections are done,
+/// and moves on to the menu when they are loaded too.
+fn load_pill_textures(
+    mut commands: Commands,
+    pill_assets: Option<Res<PillAssets>>,
+    pill_textures: Option<Res<PillTextures>>,
+    catalogs: Res<Assets<PillCatalog>>,
+    asset_server: Res<AssetServer>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    // The collections are still loading
+    let pill_assets = if let Some(pill_assets) = pill_assets {
+        pill_assets
+    } else {
+        return;
+    };
+
+    let pill_textures = if let Some(pill_textures) = pill_textures {
+        pill_textures
+    } else {
+        let textures = catalogs
+            .get(&pill_assets.catalog)
+            .map(|catalog| {
+                catalog
+                    .pills
+                    .iter()
+                    .map(|pill| {
+                        (
+                            pill.texture.clone(),
+                            asset_server.load(pill.texture.as_str()),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        commands.insert_resource(PillTextures(textures));
+        return;
+    };
+
+    match asset_server.get_group_load_state(pill_textures.0.values().map(Handle::id)) {
+        LoadState::Loaded => state.set(GameState::Menu),
+        LoadState::Failed => {
+            warn!("Some pill textures failed to load");
+            state.set(GameState::Menu);
+        }
+        _ => {}
+    }
+}
@@ -14,15 +14,23 @@ fn main() {
     App::new()
         .insert_resource(Msaa::Sample8)
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Drug Test".to_string(),
-                resolution: (800., 600.).into(),
-                canvas: Some("#bevy".to_owned()),
-                ..default()
-            }),
-            ..default()
-        }))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "Drug Test".to_string(),
+                        resolution: (800., 600.).into(),
+                        canvas: Some("#bevy".to_owned()),
+                        ..default()
+                    }),
+                    ..default()
+                })
+                // Lets levels get edited while the game is running
+                .set(AssetPlugin {
+                    watch_for_changes: cfg!(debug_assertions),
+                    ..default()
+                }),
+        )
         .add_plugin(ConsolePlugin)
         .insert_resource(ConsoleConfiguration {
             keys: vec![ToggleConsoleKey::KeyCode(KeyCode::Grave)],
@@ -1,121 +1,318 @@
-use std::time::Duration;
-
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{HashMap, HashSet},
+};
 use bevy_ecs_ldtk::prelude::{FieldValue, LdtkEntity};
 use bevy_rapier2d::prelude::{ActiveCollisionTypes, Collider, RigidBody, Sensor};
-use rand::seq::IteratorRandom;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
+    Rng,
+};
+use serde::Deserialize;
+
+use crate::{
+    cleanup::cleanup,
+    loading::{PillAssets, PillTextures},
+    rng::{seed_run, GameRng},
+    GameState, WorldState,
+};
 
-use crate::{cleanup::cleanup, loading::TextureAssets, GameState, WorldState};
+/// The `Pill_Type` that lets the game pick a pill from the catalog by rarity
+pub const RANDOM_PILL_TYPE: &str = "Random";
 
 pub struct PillPlugin;
 
 impl Plugin for PillPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Pill>()
-            .add_systems((update_pill_texture, adjust_pill_scale).in_set(OnUpdate(GameState::Playing)))
-            .add_system(cleanup::<Pill>.in_schedule(OnExit(WorldState::Yes)));
+            .register_type::<PillType>()
+            .init_resource::<PillKnowledge>()
+            .add_system(
+                forget_pills
+                    .after(seed_run)
+                    .in_schedule(OnEnter(WorldState::Yes)),
+            )
+            .add_systems(
+                (
+                    create_pills,
+                    update_pill_texture.after(create_pills),
+                    adjust_pill_scale,
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(cleanup::<PillType>.in_schedule(OnExit(WorldState::Yes)));
     }
 }
 
-#[derive(Reflect, Debug, Copy, Clone, PartialEq)]
+/// Durations are in seconds
+#[derive(Reflect, FromReflect, Deserialize, Debug, Copy, Clone, PartialEq)]
 pub enum PillEffect {
     Heal { amount: f32 },
-    Speed { amount: f32, duration: Duration },
+    Speed { amount: f32, duration: f32 },
     ToxicFart,
-    Invisibility { duration: Duration },
-    Invincibility { duration: Duration },
+    Invisibility { duration: f32 },
+    Invincibility { duration: f32 },
     Sneeze,
-    Dizziness { duration: Duration },
-    Vulnerability { amount: f32, duration: Duration },
+    Dizziness { duration: f32 },
+    Vulnerability { amount: f32, duration: f32 },
+}
+
+/// Kinds of effects the player builds up tolerance to, separately from each other
+#[derive(Reflect, FromReflect, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EffectCategory {
+    Healing,
+    Speed,
+    Stealth,
+    Protection,
+    /// Side effects, and main effects that only make things worse
+    Sickness,
 }
 
 impl PillEffect {
-    pub fn positive() -> Vec<Self> {
-        vec![
-            Self::Heal { amount: 15. },
-            Self::Speed {
-                amount: 1.5,
-                duration: Duration::from_secs(5),
+    /// The tolerance the effect is weakened by as the main effect of a pill,
+    /// if it can be weakened at all. Side effects always use [`EffectCategory::Sickness`].
+    pub fn category(&self) -> Option<EffectCategory> {
+        match self {
+            PillEffect::Heal { .. } => Some(EffectCategory::Healing),
+            PillEffect::Speed { .. } => Some(EffectCategory::Speed),
+            PillEffect::Invisibility { .. } => Some(EffectCategory::Stealth),
+            PillEffect::Invincibility { .. } => Some(EffectCategory::Protection),
+            PillEffect::Dizziness { .. } | PillEffect::Vulnerability { .. } => {
+                Some(EffectCategory::Sickness)
+            }
+            PillEffect::ToxicFart | PillEffect::Sneeze => None,
+        }
+    }
+
+    /// The effect with only `potency` of its strength left, 1 being the full dose.
+    ///
+    /// Multipliers get pulled towards 1, everything else gets scaled down.
+    pub fn weakened(self, potency: f32) -> Self {
+        let multiplier = |amount: f32| 1. + (amount - 1.) * potency;
+
+        match self {
+            PillEffect::Heal { amount } => PillEffect::Heal {
+                amount: amount * potency,
             },
-            Self::ToxicFart,
-            Self::Invisibility {
-                duration: Duration::from_secs(5),
+            PillEffect::Speed { amount, duration } => PillEffect::Speed {
+                amount: multiplier(amount),
+                duration: duration * potency,
             },
-            Self::Invincibility {
-                duration: Duration::from_secs(5),
+            PillEffect::Invisibility { duration } => PillEffect::Invisibility {
+                duration: duration * potency,
             },
-        ]
-    }
-
-    pub fn negative() -> Vec<Self> {
-        vec![
-            Self::Heal { amount: -10. },
-            Self::Speed {
-                amount: 0.5,
-                duration: Duration::from_secs(5),
+            PillEffect::Invincibility { duration } => PillEffect::Invincibility {
+                duration: duration * potency,
             },
-            Self::Sneeze,
-            Self::Dizziness {
-                duration: Duration::from_secs(5),
+            PillEffect::Dizziness { duration } => PillEffect::Dizziness {
+                duration: duration * potency,
             },
-            Self::Vulnerability {
-                amount: 2.0,
-                duration: Duration::from_secs(5),
+            PillEffect::Vulnerability { amount, duration } => PillEffect::Vulnerability {
+                amount: multiplier(amount),
+                duration: duration * potency,
             },
-        ]
+            PillEffect::ToxicFart | PillEffect::Sneeze => self,
+        }
+    }
+}
+
+/// Every pill in the game, from `assets/pills/pills.catalog.ron`
+#[derive(Deserialize, TypeUuid, Clone, Debug, Default)]
+#[uuid = "9b1e7d42-3c5a-4f08-a6d1-5e2f8c0b7a19"]
+pub struct PillCatalog {
+    pub pills: Vec<PillDefinition>,
+    pub side_effects: Vec<SideEffectDefinition>,
+    /// Shuffles the pill textures every run, so the player has to find out what each pill does
+    #[serde(default)]
+    pub unidentified: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct PillDefinition {
+    /// What the `Pill_Type` field in LDtk calls the pill
+    pub id: String,
+    pub effect: PillEffect,
+    /// Path of the texture, relative to the assets folder
+    pub texture: String,
+    /// How rare the pill is when the game picks one at random,
+    /// a pill with rarity 2 shows up half as often as one with rarity 1
+    pub rarity: f32,
+    /// Ids of the side effects the pill can come with, one of them gets picked when it spawns
+    pub side_effects: Vec<String>,
+}
+
+#[derive(Reflect, FromReflect, Deserialize, Clone, Debug, PartialEq)]
+pub struct SideEffectDefinition {
+    pub id: String,
+    pub effect: PillEffect,
+}
+
+impl PillCatalog {
+    pub fn pill(&self, id: &str) -> Option<&PillDefinition> {
+        self.pills.iter().find(|pill| pill.id == id)
+    }
+
+    pub fn side_effect(&self, id: &str) -> Option<&SideEffectDefinition> {
+        self.side_effects
+            .iter()
+            .find(|side_effect| side_effect.id == id)
+    }
+
+    /// Picks a pill, rare ones less often than the common ones.
+    pub fn random_pill(&self, rng: &mut impl Rng) -> Option<&PillDefinition> {
+        let weights = self.pills.iter().map(|pill| 1. / pill.rarity.max(0.01));
+        let distribution = WeightedIndex::new(weights).ok()?;
+
+        self.pills.get(distribution.sample(rng))
     }
 }
 
-#[derive(Component, Reflect, Debug, Copy, Clone, PartialEq)]
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub struct Pill {
+    pub id: String,
     pub main_effect: PillEffect,
-    pub side_effect: PillEffect,
+    pub side_effect: Option<SideEffectDefinition>,
+    pub texture: Handle<Image>,
 }
 
 impl Pill {
-    pub fn new(main_effect: PillEffect) -> Self {
-        // Random negative effect that isn't the same category as the main effect
-        let side_effect = PillEffect::negative()
-            .iter()
-            .filter(|effect| {
-                // Checking so they're not the same enum variant
-                std::mem::discriminant(*effect) != std::mem::discriminant(&main_effect)
+    /// Makes a pill from the catalog, with a random one of its allowed side effects.
+    pub fn new(
+        definition: &PillDefinition,
+        catalog: &PillCatalog,
+        texture: Handle<Image>,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let side_effect = definition
+            .side_effects
+            .choose(rng)
+            .and_then(|id| {
+                let side_effect = catalog.side_effect(id);
+
+                if side_effect.is_none() {
+                    warn!("Pill {} has an unknown side effect {}", definition.id, id);
+                }
+
+                side_effect
             })
-            .choose(&mut rand::thread_rng())
-            .unwrap()
-            .clone();
+            .cloned();
 
         Self {
-            main_effect,
+            id: definition.id.clone(),
+            main_effect: definition.effect,
             side_effect,
+            texture,
         }
     }
+}
 
-    pub fn get_texture(&self, textures: &TextureAssets) -> Handle<Image> {
-        match self.main_effect {
-            PillEffect::Heal { .. } => textures.health_pill.clone(),
-            PillEffect::Speed { .. } => textures.speed_pill.clone(),
-            PillEffect::ToxicFart => textures.toxic_fart_pill.clone(),
-            PillEffect::Invisibility { .. } => textures.invisibility_pill.clone(),
-            PillEffect::Invincibility { .. } => textures.invincibility_pill.clone(),
-            _ => default(),
+impl Default for Pill {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            main_effect: PillEffect::Heal { amount: 0. },
+            side_effect: None,
+            texture: default(),
         }
     }
 }
 
-impl Default for Pill {
-    fn default() -> Self {
+/// What the player found out about the pills during the current run
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PillKnowledge {
+    unidentified: bool,
+    /// Texture every pill has this run, by pill id
+    appearances: HashMap<String, String>,
+    /// Pills the player already swallowed, so they know what they do
+    identified: HashSet<String>,
+    /// Side effects the player already felt, by pill id
+    side_effects: HashMap<String, Vec<String>>,
+}
+
+impl PillKnowledge {
+    /// Knowledge at the start of a run, with the textures handed out at random if the pills are unidentified.
+    pub fn new(catalog: &PillCatalog, rng: &mut impl Rng) -> Self {
+        let mut textures = catalog
+            .pills
+            .iter()
+            .map(|pill| pill.texture.clone())
+            .collect::<Vec<_>>();
+
+        if catalog.unidentified {
+            textures.shuffle(rng);
+        }
+
         Self {
-            main_effect: PillEffect::positive()[0],
-            side_effect: PillEffect::negative()[0],
+            unidentified: catalog.unidentified,
+            appearances: catalog
+                .pills
+                .iter()
+                .map(|pill| pill.id.clone())
+                .zip(textures)
+                .collect(),
+            ..default()
+        }
+    }
+
+    /// Path of the texture the pill has this run.
+    pub fn texture<'a>(&'a self, definition: &'a PillDefinition) -> &'a str {
+        // Pills added to the catalog in the middle of a run keep their own texture
+        self.appearances
+            .get(&definition.id)
+            .unwrap_or(&definition.texture)
+    }
+
+    pub fn is_identified(&self, id: &str) -> bool {
+        !self.unidentified || self.identified.contains(id)
+    }
+
+    pub fn known_side_effects(&self, id: &str) -> &[String] {
+        self.side_effects.get(id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Remembers what the swallowed pill did.
+    pub fn learn(&mut self, pill: &Pill) {
+        self.identified.insert(pill.id.clone());
+
+        if let Some(side_effect) = &pill.side_effect {
+            let known = self.side_effects.entry(pill.id.clone()).or_default();
+
+            if !known.contains(&side_effect.id) {
+                known.push(side_effect.id.clone());
+            }
+        }
+    }
+
+    /// What the player knows about the pill, e.g. `Heal (Sneeze?)`.
+    pub fn describe(&self, pill: &Pill) -> String {
+        let name = if self.is_identified(&pill.id) {
+            pill.id.as_str()
+        } else {
+            "Unknown pill"
+        };
+
+        let side_effects = self.known_side_effects(&pill.id);
+
+        if side_effects.is_empty() {
+            name.to_string()
+        } else {
+            format!("{} ({}?)", name, side_effects.join("? "))
         }
     }
 }
 
+/// Id of the pill in the catalog, set by the `Pill_Type` field in LDtk.
+/// The pill itself gets created from the catalog once it's loaded.
+#[derive(Component, Reflect, Debug, Clone, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct PillType(pub String);
+
 #[derive(Bundle)]
 pub struct PillBundle {
-    pill: Pill,
+    pill_type: PillType,
     #[bundle]
     sprite_bundle: SpriteBundle,
     name: Name,
@@ -128,7 +325,7 @@ pub struct PillBundle {
 impl Default for PillBundle {
     fn default() -> Self {
         PillBundle {
-            pill: Pill::default(),
+            pill_type: PillType::default(),
             sprite_bundle: SpriteBundle::default(),
             name: Name::new("Pill"),
             rigidbody: RigidBody::KinematicPositionBased,
@@ -148,48 +345,115 @@ impl LdtkEntity for PillBundle {
         _asset_server: &AssetServer,
         _texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
-        let mut pill_effect_str = "".to_string();
-
-        for field in &entity_instance.field_instances {
-            match field.identifier.as_str() {
-                "Pill_Type" => {
-                    if let FieldValue::Enum(enum_value) = &field.value {
-                        pill_effect_str = enum_value.clone().expect("Empty pill type");
-                    } else {
-                        panic!("Pill_Type field is not an enum value");
-                    }
+        // Problems are reported by the level validation, here we just take the fallback
+        let (pill_type, _problems) = parse_pill_fields(entity_instance);
+
+        Self {
+            pill_type,
+            ..default()
+        }
+    }
+}
+
+/// Reads the pill's type from its LDtk fields, falling back to a random pill if it's not set.
+///
+/// Whether the type is in the catalog only gets checked once the catalog is loaded.
+pub fn parse_pill_fields(
+    entity_instance: &bevy_ecs_ldtk::EntityInstance,
+) -> (PillType, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut pill_type = None;
+
+    for field in &entity_instance.field_instances {
+        match field.identifier.as_str() {
+            "Pill_Type" => match &field.value {
+                FieldValue::Enum(value) | FieldValue::String(value) => {
+                    pill_type = value.clone();
                 }
-                other => panic!("Unknown pill field: {}", other),
-            }
+                other => problems.push(format!(
+                    "Pill_Type field is not an enum or a string: {:?}",
+                    other
+                )),
+            },
+            other => problems.push(format!("Unknown pill field: {}", other)),
         }
+    }
+
+    // Leaving the field empty is the same as asking for a random pill
+    let pill_type = pill_type
+        .filter(|pill_type| !pill_type.is_empty())
+        .unwrap_or_else(|| RANDOM_PILL_TYPE.to_string());
+
+    (PillType(pill_type), problems)
+}
+
+fn create_pills(
+    mut commands: Commands,
+    pill_query: Query<(Entity, &PillType), Without<Pill>>,
+    catalogs: Res<Assets<PillCatalog>>,
+    pill_assets: Res<PillAssets>,
+    pill_textures: Res<PillTextures>,
+    asset_server: Res<AssetServer>,
+    pill_knowledge: Res<PillKnowledge>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    let catalog = if let Some(catalog) = catalogs.get(&pill_assets.catalog) {
+        catalog
+    } else {
+        return;
+    };
 
-        let pill_effect = match pill_effect_str.as_str() {
-            "Heal" => PillEffect::positive()[0],
-            "Speed" => PillEffect::positive()[1],
-            "ToxicFart" => PillEffect::positive()[2],
-            "Invisibility" => PillEffect::positive()[3],
-            "Invincibility" => PillEffect::positive()[4],
-            other => panic!("Unknown pill effect: {}", other),
+    for (pill_entity, pill_type) in pill_query.iter() {
+        let definition = if pill_type.0 == RANDOM_PILL_TYPE {
+            catalog.random_pill(&mut game_rng.pills)
+        } else {
+            catalog.pill(&pill_type.0).or_else(|| {
+                warn!("Unknown pill type {}, picking a random one", pill_type.0);
+                catalog.random_pill(&mut game_rng.pills)
+            })
         };
 
-        Self {
-            pill: Pill::new(pill_effect),
-            ..default()
-        }
+        let definition = if let Some(definition) = definition {
+            definition
+        } else {
+            warn!("The pill catalog is empty");
+            commands.entity(pill_entity).despawn_recursive();
+            continue;
+        };
+
+        commands.entity(pill_entity).insert(Pill::new(
+            definition,
+            catalog,
+            pill_textures.get(pill_knowledge.texture(definition), &asset_server),
+            &mut game_rng.pills,
+        ));
     }
 }
 
-fn adjust_pill_scale(mut query: Query<&mut Transform, Added<Pill>>) {
+/// Starts the run not knowing anything about the pills.
+fn forget_pills(
+    mut pill_knowledge: ResMut<PillKnowledge>,
+    catalogs: Res<Assets<PillCatalog>>,
+    pill_assets: Res<PillAssets>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    let catalog = if let Some(catalog) = catalogs.get(&pill_assets.catalog) {
+        catalog
+    } else {
+        return;
+    };
+
+    *pill_knowledge = PillKnowledge::new(catalog, &mut game_rng.pills);
+}
+
+fn adjust_pill_scale(mut query: Query<&mut Transform, Added<PillType>>) {
     for mut transform in query.iter_mut() {
         transform.scale = Vec2::splat(0.25).extend(1.);
     }
 }
 
-fn update_pill_texture(
-    mut query: Query<(&Pill, &mut Handle<Image>)>,
-    textures: Res<TextureAssets>,
-) {
+fn update_pill_texture(mut query: Query<(&Pill, &mut Handle<Image>), Changed<Pill>>) {
     for (pill, mut texture) in query.iter_mut() {
-        *texture = pill.get_texture(&textures);
+        *texture = pill.texture.clone();
     }
 }
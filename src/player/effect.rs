@@ -5,14 +5,18 @@ use bevy_kira_audio::{Audio, AudioControl};
 use drug_test_proc_macros::Temporary;
 
 use crate::{
-    enemy::EnemyState,
+    enemy::{hearing::NoiseEvent, EnemyState},
     loading::{AudioAssets, FontAssets},
-    pill::{Pill, PillEffect},
-    unit::Health,
+    pill::{Pill, PillEffect, PillKnowledge},
+    unit::{Health, HealthReachedZero},
     GameState,
 };
 
-use super::{ui::EffectsUI, Player};
+use super::{
+    tolerance::{Tolerance, ToleranceSettings},
+    ui::EffectsUI,
+    Player,
+};
 
 pub struct EffectPlugin;
 
@@ -23,11 +27,13 @@ impl Plugin for EffectPlugin {
             .register_type::<Invincibility>()
             .register_type::<Dizziness>()
             .register_type::<Vulnerability>()
+            .register_type::<Overdose>()
             .add_systems(effect_systems::<MovementBoost>())
             .add_systems(effect_systems::<Invisibility>())
             .add_systems(effect_systems::<Invincibility>())
             .add_systems(effect_systems::<Dizziness>())
             .add_systems(effect_systems::<Vulnerability>())
+            .add_systems(effect_systems::<Overdose>())
             .add_system(invisibility_vfx.in_set(OnUpdate(GameState::Playing)))
             .add_system(reset_invisibility_vfx.in_set(OnUpdate(GameState::Playing)));
     }
@@ -36,23 +42,70 @@ impl Plugin for EffectPlugin {
 pub fn execute_pill_effects(
     In(pills): In<Vec<Pill>>,
     mut commands: Commands,
-    mut player_query: Query<(Entity, &mut Health, &Transform), With<Player>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Health,
+            &mut Tolerance,
+            &Transform,
+            Option<&MovementBoost>,
+            Option<&Invisibility>,
+            Option<&Invincibility>,
+            Option<&Dizziness>,
+            Option<&Vulnerability>,
+            Option<&Overdose>,
+        ),
+        With<Player>,
+    >,
     mut enemy_query: Query<(&mut EnemyState, &Transform)>,
     audio: Res<Audio>,
     audio_assets: Res<AudioAssets>,
+    mut noise_events: EventWriter<NoiseEvent>,
+    mut pill_knowledge: ResMut<PillKnowledge>,
+    tolerance_settings: Res<ToleranceSettings>,
+    time: Res<Time>,
 ) {
-    let (player_entity, mut player_health, player_transform) = player_query.single_mut();
+    let (
+        player_entity,
+        mut player_health,
+        mut tolerance,
+        player_transform,
+        movement_boost,
+        invisibility,
+        invincibility,
+        dizziness,
+        vulnerability,
+        overdose,
+    ) = player_query.single_mut();
+
+    // Effects of pills taken in the same frame, or of the same pill, stack with each other too
+    let mut movement_boost = PendingEffect::new(movement_boost);
+    let mut invisibility = PendingEffect::new(invisibility);
+    let mut invincibility = PendingEffect::new(invincibility);
+    let mut dizziness = PendingEffect::new(dizziness);
+    let mut vulnerability = PendingEffect::new(vulnerability);
+    let mut overdose = PendingEffect::new(overdose);
 
     for pill in pills {
-        for effect in [pill.main_effect, pill.side_effect] {
+        pill_knowledge.learn(&pill);
+
+        if tolerance.record_dose(time.elapsed_seconds(), &tolerance_settings) {
+            overdose.add(Overdose {
+                timer: Timer::from_seconds(tolerance_settings.overdose_duration, TimerMode::Once),
+                stacks: 1,
+            });
+        }
+
+        for effect in tolerance.take_pill(&pill, &tolerance_settings) {
             match effect {
                 PillEffect::Heal { amount } => {
                     player_health.heal(amount);
                 }
                 PillEffect::Speed { amount, duration } => {
-                    commands.entity(player_entity).insert(MovementBoost {
-                        timer: Timer::new(duration, TimerMode::Once),
+                    movement_boost.add(MovementBoost {
+                        timer: Timer::from_seconds(duration, TimerMode::Once),
                         multiplier: amount,
+                        stacks: 1,
                     });
                 }
                 PillEffect::ToxicFart => {
@@ -69,52 +122,165 @@ pub fn execute_pill_effects(
                         }
                     }
 
+                    noise_events.send(NoiseEvent {
+                        position: player_transform.translation.truncate(),
+                        loudness: 300.,
+                    });
+
                     audio.play(audio_assets.fart.clone());
                 }
                 PillEffect::Invisibility { duration } => {
-                    commands.entity(player_entity).insert(Invisibility {
-                        timer: Timer::new(duration, TimerMode::Once),
+                    invisibility.add(Invisibility {
+                        timer: Timer::from_seconds(duration, TimerMode::Once),
+                        stacks: 1,
                     });
                 }
                 PillEffect::Invincibility { duration } => {
-                    commands.entity(player_entity).insert(Invincibility {
-                        timer: Timer::new(duration, TimerMode::Once),
+                    invincibility.add(Invincibility {
+                        timer: Timer::from_seconds(duration, TimerMode::Once),
+                        stacks: 1,
                     });
                 }
                 PillEffect::Vulnerability { amount, duration } => {
-                    commands.entity(player_entity).insert(Vulnerability {
+                    vulnerability.add(Vulnerability {
                         amount,
-                        timer: Timer::new(duration, TimerMode::Once),
+                        timer: Timer::from_seconds(duration, TimerMode::Once),
+                        stacks: 1,
                     });
                 }
                 PillEffect::Dizziness { duration } => {
-                    commands.entity(player_entity).insert(Dizziness {
-                        timer: Timer::new(duration, TimerMode::Once),
+                    dizziness.add(Dizziness {
+                        timer: Timer::from_seconds(duration, TimerMode::Once),
+                        stacks: 1,
                     });
                 }
                 PillEffect::Sneeze => {
-                    for (mut enemy_state, enemy_transform) in enemy_query.iter_mut() {
-                        let distance = enemy_transform
-                            .translation
-                            .truncate()
-                            .distance(player_transform.translation.truncate());
-
-                        if distance < 450.0 && !matches!(*enemy_state, EnemyState::Stun { .. }) {
-                            *enemy_state = EnemyState::Alert {
-                                target: player_transform.translation.truncate(),
-                            };
-                        }
-                    }
+                    noise_events.send(NoiseEvent {
+                        position: player_transform.translation.truncate(),
+                        loudness: 450.,
+                    });
 
                     audio.play(audio_assets.sneeze.clone());
                 }
             }
         }
     }
+
+    movement_boost.apply(&mut commands, player_entity);
+    invisibility.apply(&mut commands, player_entity);
+    invincibility.apply(&mut commands, player_entity);
+    dizziness.apply(&mut commands, player_entity);
+    vulnerability.apply(&mut commands, player_entity);
+    overdose.apply(&mut commands, player_entity);
 }
 
 pub trait Temporary {
+    fn timer(&self) -> &Timer;
     fn get_timer(&mut self) -> &mut Timer;
+    /// How many doses make up the effect
+    fn stacks(&self) -> u32;
+    fn get_stacks(&mut self) -> &mut u32;
+}
+
+/// What happens when the player gets an effect they already have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackingPolicy {
+    /// The timer starts over, the effect stays as strong as it was
+    Refresh,
+    /// The new duration gets added to what's left of the old one
+    Extend,
+    /// The strengths add up, and the effect lasts as long as the longer of the two
+    StackMagnitude,
+    /// Only the stronger of the two stays
+    StrongestWins,
+}
+
+pub trait Stackable: Temporary {
+    const STACKING: StackingPolicy;
+
+    /// How strong the effect is, for effects that can be stronger or weaker
+    fn magnitude(&self) -> f32 {
+        1.
+    }
+
+    fn set_magnitude(&mut self, _magnitude: f32) {}
+
+    /// The strength of two doses of the effect taken together
+    fn combine(first: f32, second: f32) -> f32 {
+        first + second
+    }
+
+    /// Adds another dose of the effect, following its stacking policy.
+    fn stack(&mut self, other: Self)
+    where
+        Self: Sized,
+    {
+        let stacks = self.stacks() + other.stacks();
+
+        let remaining = self.timer().remaining_secs();
+        let other_duration = other.timer().duration().as_secs_f32();
+
+        match Self::STACKING {
+            StackingPolicy::Refresh => {
+                *self.get_timer() = other.timer().clone();
+            }
+            StackingPolicy::Extend => {
+                *self.get_timer() =
+                    Timer::from_seconds(remaining + other_duration, TimerMode::Once);
+            }
+            StackingPolicy::StackMagnitude => {
+                self.set_magnitude(Self::combine(self.magnitude(), other.magnitude()));
+
+                if other_duration > remaining {
+                    *self.get_timer() = other.timer().clone();
+                }
+            }
+            StackingPolicy::StrongestWins => {
+                if other.magnitude() > self.magnitude() {
+                    *self = other;
+                } else if other.magnitude() == self.magnitude() && other_duration > remaining {
+                    *self.get_timer() = other.timer().clone();
+                }
+            }
+        }
+
+        *self.get_stacks() = stacks;
+    }
+}
+
+/// An effect of the player while the pills get applied, so it's only inserted once at the end.
+struct PendingEffect<Effect: Stackable + Component + Clone> {
+    effect: Option<Effect>,
+    changed: bool,
+}
+
+impl<Effect: Stackable + Component + Clone> PendingEffect<Effect> {
+    fn new(current: Option<&Effect>) -> Self {
+        Self {
+            effect: current.cloned(),
+            changed: false,
+        }
+    }
+
+    fn add(&mut self, effect: Effect) {
+        if let Some(current) = &mut self.effect {
+            current.stack(effect);
+        } else {
+            self.effect = Some(effect);
+        }
+
+        self.changed = true;
+    }
+
+    fn apply(self, commands: &mut Commands, entity: Entity) {
+        if !self.changed {
+            return;
+        }
+
+        if let Some(effect) = self.effect {
+            commands.entity(entity).insert(effect);
+        }
+    }
 }
 
 pub trait EffectVisuals {
@@ -137,6 +303,24 @@ fn effect_systems<Effect: Temporary + Component + EffectVisuals>() -> SystemConf
 pub struct MovementBoost {
     pub multiplier: f32,
     pub timer: Timer,
+    pub stacks: u32,
+}
+
+impl Stackable for MovementBoost {
+    const STACKING: StackingPolicy = StackingPolicy::StackMagnitude;
+
+    fn magnitude(&self) -> f32 {
+        self.multiplier
+    }
+
+    fn set_magnitude(&mut self, magnitude: f32) {
+        self.multiplier = magnitude;
+    }
+
+    /// A boost and a debuff cancel each other out no matter which one comes first
+    fn combine(first: f32, second: f32) -> f32 {
+        first * second
+    }
 }
 
 impl EffectVisuals for MovementBoost {
@@ -161,6 +345,11 @@ impl EffectVisuals for MovementBoost {
 #[reflect(Component)]
 pub struct Invisibility {
     pub timer: Timer,
+    pub stacks: u32,
+}
+
+impl Stackable for Invisibility {
+    const STACKING: StackingPolicy = StackingPolicy::Extend;
 }
 
 impl EffectVisuals for Invisibility {
@@ -177,6 +366,11 @@ impl EffectVisuals for Invisibility {
 #[reflect(Component)]
 pub struct Invincibility {
     pub timer: Timer,
+    pub stacks: u32,
+}
+
+impl Stackable for Invincibility {
+    const STACKING: StackingPolicy = StackingPolicy::Refresh;
 }
 
 impl EffectVisuals for Invincibility {
@@ -193,6 +387,11 @@ impl EffectVisuals for Invincibility {
 #[reflect(Component)]
 pub struct Dizziness {
     pub timer: Timer,
+    pub stacks: u32,
+}
+
+impl Stackable for Dizziness {
+    const STACKING: StackingPolicy = StackingPolicy::Refresh;
 }
 
 impl EffectVisuals for Dizziness {
@@ -210,6 +409,19 @@ impl EffectVisuals for Dizziness {
 pub struct Vulnerability {
     pub amount: f32,
     pub timer: Timer,
+    pub stacks: u32,
+}
+
+impl Stackable for Vulnerability {
+    const STACKING: StackingPolicy = StackingPolicy::StrongestWins;
+
+    fn magnitude(&self) -> f32 {
+        self.amount
+    }
+
+    fn set_magnitude(&mut self, magnitude: f32) {
+        self.amount = magnitude;
+    }
 }
 
 impl EffectVisuals for Vulnerability {
@@ -222,6 +434,49 @@ impl EffectVisuals for Vulnerability {
     }
 }
 
+/// Too many pills too quickly, the player loses health and can't see or walk straight
+#[derive(Reflect, Component, Clone, Default, Debug, Temporary)]
+#[reflect(Component)]
+pub struct Overdose {
+    pub timer: Timer,
+    pub stacks: u32,
+}
+
+impl Stackable for Overdose {
+    const STACKING: StackingPolicy = StackingPolicy::Refresh;
+}
+
+impl EffectVisuals for Overdose {
+    fn get_color(&self) -> Color {
+        Color::hex("8e4fa3").unwrap()
+    }
+
+    fn get_name(&self) -> String {
+        "Overdose".to_string()
+    }
+}
+
+/// Hurts the player, taking their invincibility and vulnerability into account.
+/// Every enemy attack goes through here, be it a melee hit or a projectile.
+pub fn apply_player_damage(
+    health: &mut Health,
+    damage: f32,
+    invincibility: Option<&Invincibility>,
+    vulnerability: Option<&Vulnerability>,
+) -> HealthReachedZero {
+    if invincibility.is_some() {
+        return health.take_damage(0.);
+    }
+
+    let multiplier = if let Some(vulnerability) = vulnerability {
+        vulnerability.amount
+    } else {
+        1.0
+    };
+
+    health.take_damage(damage * multiplier)
+}
+
 fn update_effect<Effect: Temporary + Component>(
     mut commands: Commands,
     mut query: Query<(Entity, &mut Effect)>,
@@ -260,6 +515,30 @@ impl<Effect: Temporary + Component + EffectVisuals> EffectBarMarker<Effect> {
     }
 }
 
+#[derive(Component, Copy, Clone, Debug, Default)]
+struct EffectNameMarker<Effect: Temporary + Component + EffectVisuals> {
+    _effect: PhantomData<Effect>,
+}
+
+impl<Effect: Temporary + Component + EffectVisuals> EffectNameMarker<Effect> {
+    fn new() -> Self {
+        Self {
+            _effect: PhantomData,
+        }
+    }
+}
+
+/// The name of the effect, with how many doses of it the player took if it's more than one
+fn effect_label<Effect: Temporary + EffectVisuals>(effect: &Effect) -> String {
+    let stacks = effect.stacks();
+
+    if stacks > 1 {
+        format!("{} x{}", effect.get_name(), stacks)
+    } else {
+        effect.get_name()
+    }
+}
+
 fn setup_effect_ui<Effect: Temporary + Component + EffectVisuals>(
     mut commands: Commands,
     effect_query: Query<&Effect, (Added<Effect>, With<Player>)>,
@@ -270,7 +549,7 @@ fn setup_effect_ui<Effect: Temporary + Component + EffectVisuals>(
 
     for effect in effect_query.iter() {
         let color = effect.get_color();
-        let name = effect.get_name();
+        let name = effect_label(effect);
 
         commands.entity(ui_parent).with_children(|parent| {
             parent
@@ -314,6 +593,7 @@ fn setup_effect_ui<Effect: Temporary + Component + EffectVisuals>(
                                     ),
                                     ..default()
                                 })
+                                .insert(EffectNameMarker::<Effect>::new())
                                 .insert(Name::new("Effect Name"));
                         });
                 });
@@ -322,14 +602,23 @@ fn setup_effect_ui<Effect: Temporary + Component + EffectVisuals>(
 }
 
 fn update_effect_ui<Effect: Temporary + Component + EffectVisuals>(
-    mut effect_query: Query<&mut Effect, With<Player>>,
+    effect_query: Query<&Effect, With<Player>>,
     mut effect_bar_query: Query<(&mut Style, &mut BackgroundColor), With<EffectBarMarker<Effect>>>,
+    mut effect_name_query: Query<&mut Text, With<EffectNameMarker<Effect>>>,
 ) {
-    for mut effect in effect_query.iter_mut() {
+    for effect in effect_query.iter() {
         for (mut style, mut bg_color) in effect_bar_query.iter_mut() {
-            style.size.width = Val::Percent((1. - effect.get_timer().percent()) * 100.0);
+            style.size.width = Val::Percent((1. - effect.timer().percent()) * 100.0);
             bg_color.0 = effect.get_color().into();
         }
+
+        let label = effect_label(effect);
+
+        for mut text in effect_name_query.iter_mut() {
+            if text.sections[0].value != label {
+                text.sections[0].value = label.clone();
+            }
+        }
     }
 }
 
@@ -366,3 +655,166 @@ fn reset_invisibility_vfx(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::pill::SideEffectDefinition;
+
+    use super::*;
+
+    fn timer(duration: f32, elapsed: f32) -> Timer {
+        let mut timer = Timer::from_seconds(duration, TimerMode::Once);
+        timer.tick(Duration::from_secs_f32(elapsed));
+        timer
+    }
+
+    #[test]
+    fn refresh_restarts_the_timer() {
+        let mut invincibility = Invincibility {
+            timer: timer(5., 3.),
+            stacks: 1,
+        };
+
+        invincibility.stack(Invincibility {
+            timer: timer(4., 0.),
+            stacks: 1,
+        });
+
+        assert_eq!(invincibility.timer().duration(), Duration::from_secs(4));
+        assert_eq!(invincibility.timer().elapsed(), Duration::ZERO);
+        assert_eq!(invincibility.stacks(), 2);
+    }
+
+    #[test]
+    fn extend_adds_the_new_duration_to_what_is_left() {
+        let mut invisibility = Invisibility {
+            timer: timer(5., 2.),
+            stacks: 1,
+        };
+
+        invisibility.stack(Invisibility {
+            timer: timer(5., 0.),
+            stacks: 1,
+        });
+
+        assert!((invisibility.timer().remaining_secs() - 8.).abs() < 1e-4);
+        assert_eq!(invisibility.stacks(), 2);
+    }
+
+    #[test]
+    fn stack_magnitude_combines_strengths_and_keeps_the_longer_timer() {
+        let mut movement_boost = MovementBoost {
+            multiplier: 1.5,
+            timer: timer(5., 4.),
+            stacks: 1,
+        };
+
+        movement_boost.stack(MovementBoost {
+            multiplier: 2.,
+            timer: timer(3., 0.),
+            stacks: 1,
+        });
+
+        assert!((movement_boost.multiplier - 3.).abs() < 1e-4);
+        assert_eq!(movement_boost.timer().duration(), Duration::from_secs(3));
+        assert_eq!(movement_boost.stacks(), 2);
+
+        // The timer with more time left stays
+        movement_boost.stack(MovementBoost {
+            multiplier: 1.,
+            timer: timer(1., 0.),
+            stacks: 1,
+        });
+
+        assert_eq!(movement_boost.timer().duration(), Duration::from_secs(3));
+        assert_eq!(movement_boost.stacks(), 3);
+    }
+
+    #[test]
+    fn strongest_wins_keeps_the_stronger_effect() {
+        let mut vulnerability = Vulnerability {
+            amount: 2.,
+            timer: timer(5., 1.),
+            stacks: 1,
+        };
+
+        vulnerability.stack(Vulnerability {
+            amount: 1.5,
+            timer: timer(10., 0.),
+            stacks: 1,
+        });
+
+        assert_eq!(vulnerability.amount, 2.);
+        assert_eq!(vulnerability.timer().duration(), Duration::from_secs(5));
+        assert_eq!(vulnerability.stacks(), 2);
+
+        vulnerability.stack(Vulnerability {
+            amount: 3.,
+            timer: timer(2., 0.),
+            stacks: 1,
+        });
+
+        assert_eq!(vulnerability.amount, 3.);
+        assert_eq!(vulnerability.timer().duration(), Duration::from_secs(2));
+        assert_eq!(vulnerability.stacks(), 3);
+    }
+
+    #[test]
+    fn strongest_wins_refreshes_an_equally_strong_effect() {
+        let mut vulnerability = Vulnerability {
+            amount: 2.,
+            timer: timer(5., 4.),
+            stacks: 1,
+        };
+
+        vulnerability.stack(Vulnerability {
+            amount: 2.,
+            timer: timer(5., 0.),
+            stacks: 1,
+        });
+
+        assert_eq!(vulnerability.timer().elapsed(), Duration::ZERO);
+        assert_eq!(vulnerability.stacks(), 2);
+    }
+
+    #[test]
+    fn speed_pill_with_slowness_side_effect() {
+        let pill = Pill {
+            id: "Speed".to_string(),
+            main_effect: PillEffect::Speed {
+                amount: 1.5,
+                duration: 5.,
+            },
+            side_effect: Some(SideEffectDefinition {
+                id: "Slowness".to_string(),
+                effect: PillEffect::Speed {
+                    amount: 0.5,
+                    duration: 5.,
+                },
+            }),
+            texture: default(),
+        };
+
+        let mut tolerance = Tolerance::default();
+        let mut movement_boost = PendingEffect::<MovementBoost>::new(None);
+
+        for effect in tolerance.take_pill(&pill, &ToleranceSettings::default()) {
+            if let PillEffect::Speed { amount, duration } = effect {
+                movement_boost.add(MovementBoost {
+                    multiplier: amount,
+                    timer: Timer::from_seconds(duration, TimerMode::Once),
+                    stacks: 1,
+                });
+            }
+        }
+
+        // The boost and the slowness cancel each other out to a slight slowdown
+        let movement_boost = movement_boost.effect.unwrap();
+        assert!((movement_boost.multiplier - 0.75).abs() < 1e-4);
+        assert_eq!(movement_boost.stacks(), 2);
+        assert_eq!(movement_boost.get_name(), "Speed Debuff");
+        assert_eq!(effect_label(&movement_boost), "Speed Debuff x2");
+    }
+}
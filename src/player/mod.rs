@@ -12,8 +12,8 @@ use bevy_spritesheet_animation::{
 use crate::{
     actions::{Actions, BurstActions},
     cleanup::cleanup,
-    enemy::EnemyState,
-    level::PlayerSpawn,
+    enemy::{hearing::NoiseEvent, EnemyState},
+    level::{LevelHotReload, PlayerSpawn},
     loading::{TextureAssets, AudioAssets},
     pill::Pill,
     unit::{Direction, Euler, Health, Movement},
@@ -21,13 +21,18 @@ use crate::{
 };
 
 use self::{
-    effect::{execute_pill_effects, Dizziness, EffectPlugin, MovementBoost},
+    effect::{execute_pill_effects, Dizziness, EffectPlugin, MovementBoost, Overdose},
     inventory::Inventory,
-    ui::{setup_ui, update_health_ui, update_inventory_ui, HealthUI, InventorySlotUI, PlayerUI},
+    tolerance::{overdose_drift, Tolerance, TolerancePlugin, ToleranceSettings},
+    ui::{
+        setup_ui, update_health_ui, update_inventory_labels_ui, update_inventory_ui,
+        update_seed_ui, HealthUI, InventoryLabelUI, InventorySlotUI, PlayerUI, SeedUI,
+    },
 };
 
 pub mod effect;
 mod inventory;
+mod tolerance;
 mod ui;
 
 pub struct PlayerPlugin;
@@ -39,18 +44,25 @@ impl Plugin for PlayerPlugin {
             .register_type::<PlayerUI>()
             .register_type::<HealthUI>()
             .register_type::<InventorySlotUI>()
+            .register_type::<InventoryLabelUI>()
+            .register_type::<SeedUI>()
             .add_plugin(EffectPlugin)
-            .add_systems((setup_player, setup_ui).in_schedule(OnEnter(WorldState::Yes)))
+            .add_plugin(TolerancePlugin)
+            .add_system(setup_player.in_schedule(OnEnter(WorldState::Yes)))
+            .add_system(setup_ui.in_schedule(OnEnter(GameState::Playing)))
             .add_systems(
                 (
                     move_to_spawn,
                     player_movement,
                     punch_enemies.after(transition_animations),
+                    make_running_noise,
                     pick_up_pills,
                     consume_pills.pipe(execute_pill_effects),
                     update_sprite,
                     update_health_ui,
                     update_inventory_ui,
+                    update_inventory_labels_ui,
+                    update_seed_ui,
                     damage_yourself,
                 )
                     .in_set(OnUpdate(GameState::Playing)),
@@ -75,6 +87,9 @@ pub struct PlayerBody;
 #[reflect(Component)]
 pub struct PunchTimer(pub Timer);
 
+pub const PLAYER_COLLIDER_WIDTH: f32 = 42.;
+pub const PLAYER_COLLIDER_HEIGHT: f32 = 106.;
+
 #[derive(Bundle)]
 struct PlayerBundle {
     player: Player,
@@ -90,6 +105,7 @@ struct PlayerBundle {
     direction: Direction,
     health: Health,
     inventory: Inventory,
+    tolerance: Tolerance,
 }
 
 fn setup_player(mut commands: Commands, textures: Res<TextureAssets>) {
@@ -149,7 +165,7 @@ fn setup_player(mut commands: Commands, textures: Res<TextureAssets>) {
         },
         animation_manager,
         rigidbody: RigidBody::KinematicPositionBased,
-        collider: Collider::cuboid(21., 53.),
+        collider: Collider::cuboid(PLAYER_COLLIDER_WIDTH / 2., PLAYER_COLLIDER_HEIGHT / 2.),
         name: Name::new("Player"),
         movement: Movement {
             speed: 200.0,
@@ -159,15 +175,22 @@ fn setup_player(mut commands: Commands, textures: Res<TextureAssets>) {
         direction: Direction::Down,
         health: Health::default(),
         inventory: Inventory::new(3),
+        tolerance: Tolerance::default(),
     });
 }
 
 fn move_to_spawn(
     mut player_query: Query<&mut Transform, With<Player>>,
     player_spawn_query: Query<&Transform, (Added<PlayerSpawn>, Without<Player>)>,
+    hot_reload: Res<LevelHotReload>,
 ) {
     let mut player_transform = player_query.single_mut();
     if let Ok(spawn_transform) = player_spawn_query.get_single() {
+        // The level was only reloaded from disk, so the player stays where they were
+        if hot_reload.in_progress() {
+            return;
+        }
+
         player_transform.translation = spawn_transform
             .translation
             .truncate()
@@ -186,11 +209,13 @@ pub fn player_movement(
             &Movement,
             Option<&MovementBoost>,
             Option<&Dizziness>,
+            Option<&Overdose>,
         ),
         With<Player>,
     >,
     rapier_context: Res<RapierContext>,
     actions: Res<Actions>,
+    tolerance_settings: Res<ToleranceSettings>,
     time: Res<Time>,
 ) {
     for (
@@ -202,6 +227,7 @@ pub fn player_movement(
         movement,
         movement_boost,
         dizziness,
+        overdose,
     ) in player_query.iter_mut()
     {
         let speed = movement.speed * time.delta_seconds();
@@ -221,6 +247,18 @@ pub fn player_movement(
             *direction = Direction::from(Euler::from_radians(angle));
         }
 
+        // Added after the facing, so the player staggers without spinning around
+        let movement_vector = if overdose.is_some() {
+            movement_vector
+                + overdose_drift(
+                    time.elapsed_seconds(),
+                    time.delta_seconds(),
+                    &tolerance_settings,
+                )
+        } else {
+            movement_vector
+        };
+
         let horizontal_vector = Vec2::new(movement_vector.x, 0.);
         let vertical_vector = Vec2::new(0., movement_vector.y);
 
@@ -325,6 +363,9 @@ fn spawn_player_body(
     }
 }
 
+/// How much health a single punch takes from an enemy
+const PUNCH_DAMAGE: f32 = 25.;
+
 fn punch_enemies(
     mut player_query: Query<
         (
@@ -335,11 +376,12 @@ fn punch_enemies(
         ),
         With<Player>,
     >,
-    mut enemy_query: Query<(&mut EnemyState, &Transform)>,
+    mut enemy_query: Query<(&mut EnemyState, &mut Health, &Transform)>,
     mut burst_actions: EventReader<BurstActions>,
     time: Res<Time>,
     audio: Res<Audio>,
     audio_assets: Res<AudioAssets>,
+    mut noise_events: EventWriter<NoiseEvent>,
 ) {
     let (mut punch_timer, mut animation_manager, player_transform, player_direction) =
         player_query.single_mut();
@@ -357,18 +399,23 @@ fn punch_enemies(
             continue;
         }
 
-        for (mut enemy_state, _) in enemy_query.iter_mut().filter(|(_, enemy_transform)| {
-            let vector =
-                enemy_transform.translation.truncate() - player_transform.translation.truncate();
+        for (mut enemy_state, mut enemy_health, _) in
+            enemy_query.iter_mut().filter(|(_, _, enemy_transform)| {
+                let vector = enemy_transform.translation.truncate()
+                    - player_transform.translation.truncate();
 
-            let angle = vector.angle_between(Vec2::new(0., 1.));
+                let angle = vector.angle_between(Vec2::new(0., 1.));
 
-            vector.length() < 50.0
-                && *player_direction == Direction::from(Euler::from_radians(angle))
-        }) {
-            *enemy_state = EnemyState::Stun {
-                timer: Timer::from_seconds(1.5, TimerMode::Once),
-            };
+                vector.length() < 50.0
+                    && *player_direction == Direction::from(Euler::from_radians(angle))
+            })
+        {
+            // A knocked out enemy doesn't need to be stunned anymore
+            if !*enemy_health.take_damage(PUNCH_DAMAGE) {
+                *enemy_state = EnemyState::Stun {
+                    timer: Timer::from_seconds(1.5, TimerMode::Once),
+                };
+            }
         }
 
         animation_manager
@@ -377,10 +424,50 @@ fn punch_enemies(
 
         audio.play(audio_assets.punch.clone()).with_volume(0.5);
 
+        noise_events.send(NoiseEvent {
+            position: player_transform.translation.truncate(),
+            loudness: 200.,
+        });
+
         punch_timer.reset();
     }
 }
 
+/// How often the footsteps of a running player can be heard, in seconds
+const RUNNING_NOISE_INTERVAL: f32 = 0.4;
+
+/// The enemies can hear the player running while under a speed boost
+fn make_running_noise(
+    player_query: Query<(&Transform, &MovementBoost), With<Player>>,
+    actions: Res<Actions>,
+    time: Res<Time>,
+    mut since_last_step: Local<f32>,
+    mut noise_events: EventWriter<NoiseEvent>,
+) {
+    let (player_transform, movement_boost) = if let Ok(player) = player_query.get_single() {
+        player
+    } else {
+        return;
+    };
+
+    if actions.player_movement == Vec2::ZERO || movement_boost.multiplier <= 1. {
+        return;
+    }
+
+    *since_last_step += time.delta_seconds();
+
+    if *since_last_step < RUNNING_NOISE_INTERVAL {
+        return;
+    }
+
+    *since_last_step = 0.;
+
+    noise_events.send(NoiseEvent {
+        position: player_transform.translation.truncate(),
+        loudness: 100. * movement_boost.multiplier,
+    });
+}
+
 fn damage_yourself(
     mut player_query: Query<&mut Health, With<Player>>,
     keyboard: Res<Input<KeyCode>>,
@@ -406,7 +493,7 @@ fn pick_up_pills(
 
     for (pill_entity, pill) in pill_query.iter() {
         if rapier_context.intersection_pair(pill_entity, player_entity) == Some(true)
-            && inventory.add_pill(*pill)
+            && inventory.add_pill(pill.clone())
         {
             commands.entity(pill_entity).despawn();
         }
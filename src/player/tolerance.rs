@@ -0,0 +1,215 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    cleanup::cleanup,
+    pill::{EffectCategory, Pill, PillEffect},
+    unit::Health,
+    GameState,
+};
+
+use super::{effect::Overdose, Player};
+
+pub struct TolerancePlugin;
+
+impl Plugin for TolerancePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Tolerance>()
+            .register_type::<EffectCategory>()
+            .init_resource::<ToleranceSettings>()
+            .add_systems(
+                (decay_tolerance, overdose_health_drain, update_overdose_haze)
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(cleanup::<OverdoseHaze>.in_schedule(OnExit(GameState::Playing)));
+    }
+}
+
+/// How fast the player gets used to pills and how much it takes to overdose
+#[derive(Resource, Clone, Debug)]
+pub struct ToleranceSettings {
+    /// How much weaker every dose makes the next one of the same kind, 0.3 being 30 % weaker
+    pub per_dose: f32,
+    /// Doses never get weaker than `1 - max_tolerance`
+    pub max_tolerance: f32,
+    /// Tolerance lost per second
+    pub decay: f32,
+    /// Pills taken less than this many seconds apart count towards an overdose
+    pub overdose_window: f32,
+    /// Taking this many pills within the window is an overdose
+    pub overdose_pills: usize,
+    /// How long an overdose lasts, in seconds
+    pub overdose_duration: f32,
+    /// Health lost per second while overdosed
+    pub overdose_health_drain: f32,
+    /// How fast the player staggers around while overdosed
+    pub overdose_drift: f32,
+}
+
+impl Default for ToleranceSettings {
+    fn default() -> Self {
+        Self {
+            per_dose: 0.3,
+            max_tolerance: 0.75,
+            decay: 0.03,
+            overdose_window: 10.,
+            overdose_pills: 4,
+            overdose_duration: 8.,
+            overdose_health_drain: 4.,
+            overdose_drift: 60.,
+        }
+    }
+}
+
+/// How used to every kind of effect the player is, and when they took their last pills
+#[derive(Reflect, Component, Clone, Default, Debug)]
+#[reflect(Component)]
+pub struct Tolerance {
+    levels: HashMap<EffectCategory, f32>,
+    /// When the pills within the overdose window were taken, in seconds since startup
+    recent_doses: Vec<f32>,
+}
+
+impl Tolerance {
+    pub fn level(&self, category: EffectCategory) -> f32 {
+        self.levels.get(&category).copied().unwrap_or(0.)
+    }
+
+    /// Weakens the effects of a pill by the tolerance built up so far, and builds it up further.
+    ///
+    /// Every effect of the pill is weakened by the tolerance from before it was taken,
+    /// so a pill doesn't weaken its own side effect. Side effects all count as
+    /// [`EffectCategory::Sickness`], whatever they do, so e.g. a poisoning side effect
+    /// doesn't make healing pills weaker. Each category only builds up once per pill.
+    pub fn take_pill(&mut self, pill: &Pill, settings: &ToleranceSettings) -> Vec<PillEffect> {
+        let effects = std::iter::once((pill.main_effect, pill.main_effect.category()))
+            .chain(
+                pill.side_effect
+                    .as_ref()
+                    .map(|side_effect| (side_effect.effect, Some(EffectCategory::Sickness))),
+            )
+            .collect::<Vec<_>>();
+
+        let weakened = effects
+            .iter()
+            .map(|(effect, category)| {
+                if let Some(category) = category {
+                    effect.weakened(1. - self.level(*category))
+                } else {
+                    *effect
+                }
+            })
+            .collect();
+
+        let categories = effects
+            .iter()
+            .filter_map(|(_, category)| *category)
+            .collect::<HashSet<_>>();
+
+        for category in categories {
+            let level = self.levels.entry(category).or_insert(0.);
+            *level = (*level + settings.per_dose).min(settings.max_tolerance);
+        }
+
+        weakened
+    }
+
+    /// Remembers when a pill was taken.
+    ///
+    /// # Returns
+    /// Whether the player took too many pills too quickly.
+    pub fn record_dose(&mut self, now: f32, settings: &ToleranceSettings) -> bool {
+        self.recent_doses
+            .retain(|dose| now - *dose < settings.overdose_window);
+        self.recent_doses.push(now);
+
+        self.recent_doses.len() >= settings.overdose_pills
+    }
+
+    fn decay(&mut self, amount: f32) {
+        for level in self.levels.values_mut() {
+            *level = (*level - amount).max(0.);
+        }
+
+        self.levels.retain(|_, level| *level > 0.);
+    }
+}
+
+fn decay_tolerance(
+    mut player_query: Query<&mut Tolerance, With<Player>>,
+    settings: Res<ToleranceSettings>,
+    time: Res<Time>,
+) {
+    for mut tolerance in player_query.iter_mut() {
+        tolerance.decay(settings.decay * time.delta_seconds());
+    }
+}
+
+fn overdose_health_drain(
+    mut player_query: Query<&mut Health, (With<Player>, With<Overdose>)>,
+    settings: Res<ToleranceSettings>,
+    time: Res<Time>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    for mut health in player_query.iter_mut() {
+        if *health.take_damage(settings.overdose_health_drain * time.delta_seconds()) {
+            state.set(GameState::GameOver);
+        }
+    }
+}
+
+/// How far the player staggers this frame because of an overdose.
+pub fn overdose_drift(elapsed: f32, delta: f32, settings: &ToleranceSettings) -> Vec2 {
+    // Two slow waves that don't line up, so the drift never settles into a pattern
+    Vec2::new((elapsed * 1.3).sin(), (elapsed * 0.7).cos()) * settings.overdose_drift * delta
+}
+
+/// Blurs the player's vision while they are overdosed
+#[derive(Component, Clone, Copy, Debug, Default)]
+struct OverdoseHaze;
+
+/// Spawns the haze while the player is overdosed and despawns it again afterwards.
+fn update_overdose_haze(
+    mut commands: Commands,
+    overdose_query: Query<&Overdose, With<Player>>,
+    mut haze_query: Query<(Entity, &mut BackgroundColor), With<OverdoseHaze>>,
+    time: Res<Time>,
+) {
+    let overdose = if let Ok(overdose) = overdose_query.get_single() {
+        overdose
+    } else {
+        for (haze_entity, _) in haze_query.iter() {
+            commands.entity(haze_entity).despawn_recursive();
+        }
+
+        return;
+    };
+
+    // Fades in and out, and clears up as the overdose wears off
+    let pulse = 0.5 + 0.5 * (time.elapsed_seconds() * 2.).sin();
+    let strength = 1. - overdose.timer.percent();
+    let color = Color::rgba(0.55, 0.35, 0.6, (0.2 + 0.25 * pulse) * strength);
+
+    if haze_query.is_empty() {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                background_color: color.into(),
+                z_index: ZIndex::Global(-1),
+                ..default()
+            },
+            OverdoseHaze,
+            Name::new("Overdose Haze"),
+        ));
+    }
+
+    for (_, mut background_color) in haze_query.iter_mut() {
+        background_color.0 = color;
+    }
+}
@@ -1,9 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{
-    loading::{FontAssets, TextureAssets},
-    unit::Health,
-};
+use crate::{loading::FontAssets, pill::PillKnowledge, rng::GameRng, unit::Health};
 
 use super::{inventory::Inventory, Player};
 
@@ -21,10 +18,22 @@ pub struct InventorySlotUI {
     pub index: usize,
 }
 
+/// What the player knows about the pill in the inventory slot
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Component)]
+pub struct InventoryLabelUI {
+    pub index: usize,
+}
+
 #[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
 #[reflect(Component)]
 pub struct EffectsUI;
 
+/// Shows the seed of the run, so it can be put in bug reports
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Component)]
+pub struct SeedUI;
+
 pub fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
     commands
         .spawn(NodeBundle {
@@ -192,6 +201,32 @@ pub fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                         });
                 });
 
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(5.0)),
+                        flex_direction: FlexDirection::Column,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(Name::new("Inventory Labels Container"))
+                .with_children(|parent| {
+                    for index in 0..3 {
+                        parent
+                            .spawn(TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: font_assets.fira_sans.clone(),
+                                    font_size: 16.0,
+                                    color: Color::WHITE,
+                                },
+                            ))
+                            .insert(InventoryLabelUI { index })
+                            .insert(Name::new(format!("Inventory Label #{}", index + 1)));
+                    }
+                });
+
             parent
                 .spawn(NodeBundle {
                     style: Style {
@@ -203,6 +238,29 @@ pub fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                 })
                 .insert(EffectsUI)
                 .insert(Name::new("Effect Bars Container"));
+
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font_assets.space_grotesk.clone(),
+                            font_size: 16.0,
+                            color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            right: Val::Px(10.0),
+                            bottom: Val::Px(10.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }),
+                )
+                .insert(SeedUI)
+                .insert(Name::new("Seed UI"));
         });
 }
 
@@ -223,7 +281,6 @@ pub fn update_health_ui(
 pub fn update_inventory_ui(
     player_query: Query<&Inventory, With<Player>>,
     mut inventory_ui_query: Query<(&mut UiImage, &InventorySlotUI)>,
-    textures: Res<TextureAssets>,
 ) {
     let player_inventory = player_query.single();
 
@@ -231,9 +288,31 @@ pub fn update_inventory_ui(
         let item = player_inventory.get_pill(inventory_slot_ui.index);
 
         if let Some(item) = item {
-            inventory_ui.texture = item.get_texture(&textures);
+            inventory_ui.texture = item.texture.clone();
         } else {
             inventory_ui.texture = default();
         }
     }
 }
+
+pub fn update_inventory_labels_ui(
+    player_query: Query<&Inventory, With<Player>>,
+    mut label_ui_query: Query<(&mut Text, &InventoryLabelUI)>,
+    pill_knowledge: Res<PillKnowledge>,
+) {
+    let player_inventory = player_query.single();
+
+    for (mut label_ui, label) in label_ui_query.iter_mut() {
+        label_ui.sections[0].value = if let Some(pill) = player_inventory.get_pill(label.index) {
+            format!("{}: {}", label.index + 1, pill_knowledge.describe(pill))
+        } else {
+            String::new()
+        };
+    }
+}
+
+pub fn update_seed_ui(game_rng: Res<GameRng>, mut seed_ui_query: Query<&mut Text, With<SeedUI>>) {
+    for mut seed_ui in seed_ui_query.iter_mut() {
+        seed_ui.sections[0].value = format!("seed {}", game_rng.seed());
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use bevy_console::PrintConsoleLine;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::WorldState;
+
+/// Environment variable that makes every run use the same seed, e.g. the one from a bug report
+pub const SEED_ENV_VAR: &str = "DRUG_TEST_SEED";
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameRng::new(0))
+            .add_system(seed_run.in_schedule(OnEnter(WorldState::Yes)));
+    }
+}
+
+/// Every random thing that happens in the game comes from here,
+/// so playing with the same seed plays out the same way.
+///
+/// Each part of the game draws from its own stream, so e.g. an enemy
+/// shooting one more time doesn't change which pills show up.
+#[derive(Resource, Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    /// Which pills spawn and what side effects they come with
+    pub pills: StdRng,
+    /// Where enemies wander and search
+    pub ai: StdRng,
+    /// Damage rolls and bullet spread
+    pub combat: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pills: Self::stream(seed, 0),
+            ai: Self::stream(seed, 1),
+            combat: Self::stream(seed, 2),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn stream(seed: u64, index: u64) -> StdRng {
+        // Spreads the streams far apart, so seeds next to each other don't share any of them
+        StdRng::seed_from_u64(seed ^ index.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+}
+
+/// The seed of the run, taken from `DRUG_TEST_SEED` if it's set and random otherwise.
+fn run_seed() -> u64 {
+    if let Ok(value) = std::env::var(SEED_ENV_VAR) {
+        match value.trim().parse() {
+            Ok(seed) => return seed,
+            Err(_) => warn!("{} is not a valid seed: {}", SEED_ENV_VAR, value),
+        }
+    }
+
+    rand::random()
+}
+
+pub fn seed_run(mut game_rng: ResMut<GameRng>, mut console_line: EventWriter<PrintConsoleLine>) {
+    *game_rng = GameRng::new(run_seed());
+
+    info!("Starting a run with seed {}", game_rng.seed());
+    console_line.send(PrintConsoleLine::new(
+        format!("seed {}", game_rng.seed()).into(),
+    ));
+}
@@ -72,6 +72,18 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    /// Unit vector pointing in the direction.
+    pub fn as_vec2(&self) -> Vec2 {
+        match self {
+            Direction::Up => Vec2::Y,
+            Direction::Down => Vec2::NEG_Y,
+            Direction::Left => Vec2::NEG_X,
+            Direction::Right => Vec2::X,
+        }
+    }
+}
+
 #[derive(Deref, DerefMut, Copy, Clone, Debug, PartialEq, PartialOrd)]
 pub struct Euler(pub f32);
 